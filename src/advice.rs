@@ -0,0 +1,14 @@
+/// Expected access pattern of a memory mapped segment.
+///
+/// Every variant maps to a `libc::madvise` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessPattern {
+    /// Pages will be access in a near future (`MADV_WILLNEED`).
+    WillNeed,
+    /// Pages are not needed for now and can be reloaded from disk later (`MADV_DONTNEED`).
+    DontNeed,
+    /// Pages will be read in order, so read-ahead can be aggressive (`MADV_SEQUENTIAL`).
+    Sequential,
+    /// Pages will be read in random order, so read-ahead is useless (`MADV_RANDOM`).
+    Random,
+}
@@ -0,0 +1,108 @@
+use std::{
+    io,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{segment::msync, Segment};
+
+/// Mapped region the flush thread is allowed to sync.
+///
+/// Address is stored as `usize` so it can be shared with the flush thread.
+#[derive(Debug)]
+struct State {
+    addr: usize,
+    size: usize,
+    stopped: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // Flush thread never panic while holding the lock, but let's be safe.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Handle to a thread that periodically calls `msync` on a segment.
+///
+/// # Threading model
+///
+/// The thread never owns the segment. It only knows its address and size
+/// through a shared mutex protected state.
+///
+/// Each time the owner vec remap its segment, it has to call `update` **before**
+/// unmapping the old one. Since `msync` is only called while holding the lock,
+/// this guarantees the thread never access unmapped memory.
+///
+/// Dropping this struct stops and joins the thread.
+#[derive(Debug)]
+pub(crate) struct BackgroundFlush {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BackgroundFlush {
+    pub(crate) fn spawn<T>(segment: &Segment<T>, interval: Duration) -> io::Result<Self> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                addr: segment.addr as usize,
+                size: segment.disk_size(),
+                stopped: false,
+            }),
+            cond: Condvar::new(),
+        });
+
+        let thread_shared = shared.clone();
+        let handle = thread::Builder::new()
+            .name("mmap-vec-flush".to_string())
+            .spawn(move || flush_loop(&thread_shared, interval))?;
+
+        Ok(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    /// Update region to flush.
+    pub(crate) fn update<T>(&self, segment: &Segment<T>) {
+        let mut state = self.shared.lock();
+        state.addr = segment.addr as usize;
+        state.size = segment.disk_size();
+    }
+}
+
+impl Drop for BackgroundFlush {
+    fn drop(&mut self) {
+        self.shared.lock().stopped = true;
+        self.shared.cond.notify_all();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn flush_loop(shared: &Shared, interval: Duration) {
+    let mut state = shared.lock();
+
+    // Thread may be stopped before it even started.
+    while !state.stopped {
+        state = match shared.cond.wait_timeout(state, interval) {
+            Ok((state, _)) => state,
+            Err(err) => err.into_inner().0,
+        };
+
+        if !state.stopped && state.addr != 0 && state.size != 0 {
+            // Nothing can be done here with errors. User can still call flush
+            // manually to get them.
+            let _ = unsafe { msync(state.addr as *mut libc::c_void, state.size) };
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::{io, mem, ptr};
+
+use crate::{utils::page_size, DefaultSegmentBuilder, MmapVec, MmapVecError, SegmentBuilder};
+
+/// A disk memory mapped vector buffering pushes in RAM and committing them in batches.
+///
+/// # How it works ?
+///
+/// Pushed values are first stored in a small RAM staging buffer.
+/// When it holds `batch_len` values (one page worth of data by default), they are
+/// all moved to the inner vec at once: the segment is touched, and possibly grown,
+/// once per batch instead of once per value.
+///
+/// Values still in the buffer are not visible in the mapping: they are lost if the
+/// process crashes. `commit` can be called at any time to shorten this window.
+/// Buffer is committed when the vec is dropped, and by `into_inner`.
+///
+/// Example:
+/// ```rust
+/// # use mmap_vec::BufferedMmapVec;
+/// let mut log = BufferedMmapVec::<u64>::with_batch_len(4);
+///
+/// for i in 0..6 {
+///     log.push(i).unwrap();
+/// }
+/// assert_eq!(log.len(), 6);
+/// assert_eq!(&log.committed()[..], [0, 1, 2, 3]);
+///
+/// let v = log.into_inner().unwrap();
+/// assert_eq!(&v[..], [0, 1, 2, 3, 4, 5]);
+/// ```
+#[derive(Debug)]
+pub struct BufferedMmapVec<T, B: SegmentBuilder = DefaultSegmentBuilder> {
+    inner: MmapVec<T, B>,
+    buffer: Vec<T>,
+    batch_len: usize,
+}
+
+impl<T, B> BufferedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    /// Create a zero size buffered mmap vec, committing one page of values at a time.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::from(MmapVec::new())
+    }
+
+    /// Create a zero size buffered mmap vec, committing `batch_len` values at a time.
+    ///
+    /// Will panic if `batch_len` is 0.
+    #[inline(always)]
+    pub fn with_batch_len(batch_len: usize) -> Self {
+        Self::from_vec_with_batch_len(MmapVec::new(), batch_len)
+    }
+
+    /// Buffer pushes to `inner`, committing `batch_len` values at a time.
+    ///
+    /// Will panic if `batch_len` is 0.
+    pub fn from_vec_with_batch_len(inner: MmapVec<T, B>, batch_len: usize) -> Self {
+        assert!(batch_len > 0, "Batch len cannot be 0");
+        Self {
+            inner,
+            buffer: Vec::with_capacity(batch_len),
+            batch_len,
+        }
+    }
+
+    /// Number of values pushed, committed or not.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len() + self.buffer.len()
+    }
+
+    /// Returns `true` if no value has been pushed.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of values waiting in the staging buffer.
+    #[inline(always)]
+    pub fn pending_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Number of values committed at once.
+    #[inline(always)]
+    pub fn batch_len(&self) -> usize {
+        self.batch_len
+    }
+
+    /// Inner vec, holding committed values only.
+    #[inline(always)]
+    pub fn committed(&self) -> &MmapVec<T, B> {
+        &self.inner
+    }
+
+    /// Append a value, committing the buffer if it is full.
+    ///
+    /// This function can fail when committing, because it depends on FS / IO calls.
+    /// Value is kept in the buffer in that case.
+    pub fn push(&mut self, value: T) -> io::Result<()> {
+        self.buffer.push(value);
+        if self.buffer.len() >= self.batch_len {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Move all buffered values to the inner vec.
+    ///
+    /// Vec grows geometrically like with `MmapVec::push`. If doubling would exceed
+    /// segment size limit, only room for buffered values is reserved.
+    /// On error, inner vec and buffer are left unchanged.
+    pub fn commit(&mut self) -> io::Result<()> {
+        let pending = self.buffer.len();
+        let len = self.inner.len();
+        if self.inner.capacity() - len < pending {
+            match self.inner.reserve(pending.max(len)) {
+                // Doubling does not fit segment size limit, buffer may still do.
+                Err(err)
+                    if matches!(
+                        err.get_ref().and_then(|err| err.downcast_ref()),
+                        Some(MmapVecError::SegmentSizeExceeded { .. })
+                    ) =>
+                {
+                    self.inner.reserve_exact(pending)?
+                }
+                result => result?,
+            }
+        }
+
+        for value in self.buffer.drain(..) {
+            // Cannot fail since enough capacity has been reserved above.
+            if self.inner.push_within_capacity(value).is_err() {
+                panic!("Fail to push to reserved capacity");
+            }
+        }
+        Ok(())
+    }
+
+    /// Commit buffer, then get back the inner mmap vec.
+    pub fn into_inner(mut self) -> io::Result<MmapVec<T, B>> {
+        self.commit()?;
+
+        let this = mem::ManuallyDrop::new(self);
+        // We are not going to use `this` anymore, and buffer is now empty.
+        unsafe {
+            drop(ptr::read(&this.buffer));
+            Ok(ptr::read(&this.inner))
+        }
+    }
+}
+
+impl<T, B> Default for BufferedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, B> From<MmapVec<T, B>> for BufferedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    fn from(inner: MmapVec<T, B>) -> Self {
+        let batch_len = (page_size() / mem::size_of::<T>()).max(1);
+        Self::from_vec_with_batch_len(inner, batch_len)
+    }
+}
+
+impl<T, B> Drop for BufferedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    fn drop(&mut self) {
+        // Best effort: values left in the buffer are drop with it.
+        let _ = self.commit();
+    }
+}
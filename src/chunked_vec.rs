@@ -0,0 +1,292 @@
+use std::{
+    io, mem,
+    ops::{Index, IndexMut},
+    path::PathBuf,
+};
+
+use crate::{
+    fs_ops::{Fs, FsOps},
+    round_capacity_to_page,
+    utils::check_zst,
+    DefaultSegmentBuilder, Segment, SegmentBuilder,
+};
+
+/// Default chunk size in bytes.
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// A disk memory mapped vector split in fixed size chunks.
+///
+/// # How it works ?
+///
+/// Unlike `MmapVec`, segments are never remapped: when the last chunk is full,
+/// a new segment (so a new file) is created and appended to the chunk list.
+///
+/// This avoids copying / remapping giant contiguous regions, at the cost of
+/// not being able to deref vec as a single slice.
+///
+/// Indexing computes chunk and offset in chunk from element index.
+///
+/// Example:
+/// ```rust
+/// # use mmap_vec::ChunkedMmapVec;
+/// let mut v = ChunkedMmapVec::<u64>::with_chunk_capacity(100);
+///
+/// for i in 0..1000 {
+///     v.push(i).unwrap();
+/// }
+///
+/// assert_eq!(v.len(), 1000);
+/// assert_eq!(v.chunk_count(), 10);
+/// assert_eq!(v[512], 512);
+/// assert!(v.iter().copied().eq(0..1000));
+/// ```
+#[derive(Debug)]
+pub struct ChunkedMmapVec<T, B: SegmentBuilder = DefaultSegmentBuilder> {
+    chunks: Vec<Segment<T>>,
+    paths: Vec<PathBuf>,
+    builder: B,
+    chunk_capacity: usize,
+    len: usize,
+}
+
+impl<T, B> ChunkedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    /// Create a zero size chunked vec.
+    ///
+    /// Chunk capacity is rounded to page size, so every chunk file is about 1MB.
+    #[inline(always)]
+    pub fn new() -> Self {
+        check_zst::<T>();
+        let chunk_capacity = round_capacity_to_page::<T>(DEFAULT_CHUNK_SIZE / mem::size_of::<T>());
+        Self::with_builder(B::default(), chunk_capacity.max(1))
+    }
+
+    /// Create a zero size chunked vec where every chunk can hold `chunk_capacity` elements.
+    ///
+    /// Will panic if `chunk_capacity` is 0.
+    #[inline(always)]
+    pub fn with_chunk_capacity(chunk_capacity: usize) -> Self {
+        Self::with_builder(B::default(), chunk_capacity)
+    }
+
+    /// Create a zero size chunked vec using given segment builder.
+    ///
+    /// No IO is done here: first chunk is created on first push.
+    ///
+    /// Will panic if `chunk_capacity` is 0.
+    pub fn with_builder(builder: B, chunk_capacity: usize) -> Self {
+        check_zst::<T>();
+        assert!(chunk_capacity > 0, "Chunk capacity cannot be 0");
+
+        Self {
+            chunks: Vec::new(),
+            paths: Vec::new(),
+            builder,
+            chunk_capacity,
+            len: 0,
+        }
+    }
+
+    /// Number of elements in the vec.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vec contains no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements every chunk can hold.
+    #[inline(always)]
+    pub fn chunk_capacity(&self) -> usize {
+        self.chunk_capacity
+    }
+
+    /// Number of allocated chunks.
+    #[inline(always)]
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Number of elements the vec can hold without creating a new chunk.
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.chunks.len() * self.chunk_capacity
+    }
+
+    /// Bytes use on disk for this vec.
+    #[inline(always)]
+    pub fn disk_size(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.disk_size()).sum()
+    }
+
+    /// Path of every chunk files, in chunk order.
+    #[inline(always)]
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Get reference to element at `index`, or `None` if out of bounds.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        self.chunks[index / self.chunk_capacity].get(index % self.chunk_capacity)
+    }
+
+    /// Get mutable reference to element at `index`, or `None` if out of bounds.
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        self.chunks[index / self.chunk_capacity].get_mut(index % self.chunk_capacity)
+    }
+
+    /// Append a value to the vec.
+    ///
+    /// If last chunk is full, a new one is created.
+    /// Existing chunks are never remapped.
+    ///
+    /// This function can fail, because it depends on FS / IO calls.
+    pub fn push(&mut self, value: T) -> io::Result<()> {
+        let chunk_index = self.len / self.chunk_capacity;
+        if chunk_index == self.chunks.len() {
+            self.add_chunk()?;
+        }
+
+        if self.chunks[chunk_index]
+            .push_within_capacity(value)
+            .is_err()
+        {
+            panic!("Chunk {chunk_index} was build with bad capacity");
+        }
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Remove last value of the vec.
+    ///
+    /// Value will be return if data structure is not empty.
+    /// Chunks are kept, so they can be reused by next pushes.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.len -= 1;
+        self.chunks[self.len / self.chunk_capacity].pop()
+    }
+
+    /// Clears the vec, removing all values.
+    ///
+    /// Chunks are kept, so they can be reused by next pushes.
+    pub fn clear(&mut self) {
+        for chunk in &mut self.chunks {
+            chunk.clear();
+        }
+        self.len = 0;
+    }
+
+    /// Iterate over all elements of the vec.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.chunks.iter().flat_map(|chunk| chunk.iter())
+    }
+
+    /// Iterate over all non empty chunks as slices.
+    pub fn chunks(&self) -> impl Iterator<Item = &[T]> + '_ {
+        self.chunks
+            .iter()
+            .map(|chunk| &chunk[..])
+            .take_while(|chunk| !chunk.is_empty())
+    }
+
+    /// Flush all chunks to disk.
+    ///
+    /// This function blocks until all pages are written.
+    pub fn flush(&self) -> io::Result<()> {
+        for chunk in &self.chunks {
+            chunk.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Create a new segment at end of chunk list.
+    fn add_chunk(&mut self) -> io::Result<()> {
+        let path = self.builder.new_segment_path();
+
+        match Segment::open_rw(&path, self.chunk_capacity) {
+            Ok(chunk) => {
+                self.chunks.push(chunk);
+                self.paths.push(path);
+                Ok(())
+            }
+            Err(err) => {
+                let _ = Fs::remove_file(&path);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<T, B> Default for ChunkedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, B> Index<usize> for ChunkedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        let len = self.len;
+        match self.get(index) {
+            Some(value) => value,
+            None => panic!("index out of bounds: the len is {len} but the index is {index}"),
+        }
+    }
+}
+
+impl<T, B> IndexMut<usize> for ChunkedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(value) => value,
+            None => panic!("index out of bounds: the len is {len} but the index is {index}"),
+        }
+    }
+}
+
+impl<T, B> Drop for ChunkedMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    fn drop(&mut self) {
+        // Unmap all chunks before removing their files.
+        self.chunks.clear();
+
+        for path in &self.paths {
+            let _ = Fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    io,
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    ptr, slice,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::{DefaultSegmentBuilder, MmapVec, SegmentBuilder};
+
+/// A disk memory mapped vector that can be appended by one producer through a shared
+/// reference, while many readers access it.
+///
+/// # How it works ?
+///
+/// Pushing within capacity does not require any lock, and never waits:
+///
+/// 1. Value is written in the slot just after published elements.
+/// 2. Slot is published by storing the new len with `Release` ordering.
+///
+/// Readers load len with `Acquire` ordering, so they only see fully written values.
+///
+/// Only a single producer can push at a time: `push_within_capacity` panics if it is
+/// called while another call is running on another thread.
+///
+/// Growing the vec requires remapping the segment, this is why `push` and `reserve`
+/// need an exclusive access.
+///
+/// Example:
+/// ```rust
+/// # use mmap_vec::ConcurrentMmapVec;
+/// let v = ConcurrentMmapVec::<u64>::with_capacity(100).unwrap();
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| {
+///         for i in 0..100 {
+///             assert!(v.push_within_capacity(i).is_ok());
+///         }
+///     });
+///     s.spawn(|| {
+///         // Reader only see fully written values.
+///         for (i, x) in v.iter().enumerate() {
+///             assert_eq!(*x, i as u64);
+///         }
+///     });
+/// });
+///
+/// assert_eq!(v.len(), 100);
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentMmapVec<T, B: SegmentBuilder = DefaultSegmentBuilder> {
+    /// Inner vec, its segment len is only updated when we have an exclusive access.
+    inner: MmapVec<T, B>,
+    /// Set while a producer is pushing, to detect concurrent producers.
+    producing: AtomicBool,
+    len: AtomicUsize,
+    _marker: PhantomData<*const T>,
+}
+
+impl<T, B> ConcurrentMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    /// Create a zero size concurrent mmap vec.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::from(MmapVec::new())
+    }
+
+    /// Create a concurrent mmap vec with a given capacity.
+    ///
+    /// This function can fail if FS / IO failed.
+    #[inline(always)]
+    pub fn with_capacity(capacity: usize) -> io::Result<Self> {
+        Ok(Self::from(MmapVec::with_capacity(capacity)?))
+    }
+
+    /// Number of elements published in the vec.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if the vec contains no published elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Currently used vec size.
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// Try to push a new value to the vec without taking any lock.
+    ///
+    /// If vec is too small, value will be return as an `Err`.
+    ///
+    /// Will panic if called concurrently from several threads: vec supports a single
+    /// producer.
+    pub fn push_within_capacity(&self, value: T) -> Result<(), T> {
+        assert!(
+            !self.producing.swap(true, Ordering::Acquire),
+            "ConcurrentMmapVec supports a single producer"
+        );
+
+        // Only the producer updates len, so it cannot change under our feet.
+        let slot = self.len.load(Ordering::Relaxed);
+        let result = if slot < self.capacity() {
+            unsafe { ptr::write(self.inner.segment.addr.add(slot), value) };
+            self.len.store(slot + 1, Ordering::Release);
+            Ok(())
+        } else {
+            Err(value)
+        };
+
+        self.producing.store(false, Ordering::Release);
+        result
+    }
+
+    /// Append a value to the vec, growing it if needed.
+    ///
+    /// This function can fail, because it depends on FS / IO calls.
+    pub fn push(&mut self, value: T) -> io::Result<()> {
+        let inner = self.sync_inner();
+        let result = inner.push(value);
+        self.store_len();
+        result
+    }
+
+    /// Reserve space for at least `additional` new elements.
+    ///
+    /// See `MmapVec::reserve` for more details.
+    pub fn reserve(&mut self, additional: usize) -> io::Result<()> {
+        self.sync_inner().reserve(additional)
+    }
+
+    /// Get back the inner mmap vec.
+    pub fn into_inner(mut self) -> MmapVec<T, B> {
+        self.sync_inner();
+
+        let this = mem::ManuallyDrop::new(self);
+        // We are not going to use `this` anymore, and it has no drop side effect
+        // except the inner vec one.
+        unsafe { ptr::read(&this.inner) }
+    }
+
+    /// Update inner segment len with published one.
+    fn sync_inner(&mut self) -> &mut MmapVec<T, B> {
+        let len = *self.len.get_mut();
+        unsafe { self.inner.segment.set_len(len) };
+        &mut self.inner
+    }
+
+    /// Update published len with inner segment one.
+    fn store_len(&mut self) {
+        let len = self.inner.len();
+        *self.len.get_mut() = len;
+    }
+}
+
+impl<T, B> Default for ConcurrentMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, B> From<MmapVec<T, B>> for ConcurrentMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    fn from(inner: MmapVec<T, B>) -> Self {
+        let len = inner.len();
+        Self {
+            inner,
+            producing: AtomicBool::new(false),
+            len: AtomicUsize::new(len),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, B> Deref for ConcurrentMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    type Target = [T];
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        let len = self.len();
+        if len == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.inner.segment.addr, len) }
+    }
+}
+
+impl<T, B> Drop for ConcurrentMmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    fn drop(&mut self) {
+        // Let inner vec drop published values.
+        self.sync_inner();
+    }
+}
+
+unsafe impl<T: Send, B: SegmentBuilder + Send> Send for ConcurrentMmapVec<T, B> {}
+unsafe impl<T: Send + Sync, B: SegmentBuilder + Sync> Sync for ConcurrentMmapVec<T, B> {}
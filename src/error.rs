@@ -0,0 +1,172 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+/// Errors that can happen while dealing with persisted segments.
+#[derive(Debug)]
+pub enum MmapVecError {
+    /// Underlying FS / IO call failed.
+    Io(io::Error),
+
+    /// Underlying FS / IO call failed on a given segment file.
+    FileIo {
+        /// Path of the segment file.
+        path: PathBuf,
+        /// Original error.
+        source: io::Error,
+    },
+
+    /// Folder where segment file should be created does not exist, or is not a folder.
+    InvalidStoreDir {
+        /// Path of the folder.
+        path: PathBuf,
+        /// Original error, from segment file creation.
+        source: io::Error,
+    },
+
+    /// Requested capacity in bytes does not fit in memory address space.
+    CapacityOverflow {
+        /// Requested capacity in elements.
+        capacity: usize,
+        /// Size of a single element in bytes.
+        element_size: usize,
+    },
+
+    /// Requested segment size in bytes is above configured limit.
+    SegmentSizeExceeded {
+        /// Requested segment size in bytes.
+        size: usize,
+        /// Configured segment size limit in bytes.
+        limit: usize,
+    },
+
+    /// File size is not a multiple of element size.
+    InvalidFileSize {
+        /// Size of the file in bytes.
+        file_size: u64,
+        /// Size of a single element in bytes.
+        element_size: usize,
+    },
+
+    /// File system does not have enough free space to grow a segment.
+    InsufficientSpace {
+        /// Number of bytes the segment file would grow by.
+        required: u64,
+        /// Number of bytes available to unprivileged users on the file system.
+        available: u64,
+    },
+
+    /// Saved vec file header is not valid.
+    InvalidHeader {
+        /// What is wrong with the header.
+        reason: &'static str,
+    },
+
+    /// Saved vec file contains elements of another size than the requested type.
+    ElementSizeMismatch {
+        /// Element size recorded in the file.
+        file_element_size: usize,
+        /// Size of a single element of the requested type.
+        element_size: usize,
+    },
+}
+
+impl fmt::Display for MmapVecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {err}"),
+            Self::FileIo { path, source } => {
+                write!(f, "io error on {}: {source}", path.display())
+            }
+            Self::InvalidStoreDir { path, source } => {
+                write!(f, "invalid store folder {}: {source}", path.display())
+            }
+            Self::CapacityOverflow {
+                capacity,
+                element_size,
+            } => write!(
+                f,
+                "capacity overflow: {capacity} elements of {element_size} bytes"
+            ),
+            Self::SegmentSizeExceeded { size, limit } => write!(
+                f,
+                "segment size exceeded: {size} bytes requested, limit is {limit} bytes"
+            ),
+            Self::InvalidFileSize {
+                file_size,
+                element_size,
+            } => write!(
+                f,
+                "invalid file size: {file_size} is not a multiple of element size {element_size}"
+            ),
+            Self::InsufficientSpace {
+                required,
+                available,
+            } => write!(
+                f,
+                "insufficient space: {required} bytes required, {available} bytes available"
+            ),
+            Self::InvalidHeader { reason } => write!(f, "invalid header: {reason}"),
+            Self::ElementSizeMismatch {
+                file_element_size,
+                element_size,
+            } => write!(
+                f,
+                "element size mismatch: file contains {file_element_size} bytes elements, expected {element_size}"
+            ),
+        }
+    }
+}
+
+impl Error for MmapVecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::FileIo { source, .. } | Self::InvalidStoreDir { source, .. } => Some(source),
+            Self::CapacityOverflow { .. }
+            | Self::SegmentSizeExceeded { .. }
+            | Self::InvalidFileSize { .. }
+            | Self::InsufficientSpace { .. }
+            | Self::InvalidHeader { .. }
+            | Self::ElementSizeMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for MmapVecError {
+    #[inline(always)]
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl MmapVecError {
+    /// Wrap `source` into an `io::Error` that also records `path`.
+    ///
+    /// Error kind is preserved, and `MmapVecError::FileIo` can be retrieved
+    /// using `io::Error::get_ref`.
+    pub(crate) fn io_with_path<P: Into<PathBuf>>(path: P, source: io::Error) -> io::Error {
+        io::Error::new(
+            source.kind(),
+            Self::FileIo {
+                path: path.into(),
+                source,
+            },
+        )
+    }
+}
+
+impl From<MmapVecError> for io::Error {
+    fn from(err: MmapVecError) -> Self {
+        match err {
+            MmapVecError::Io(err) => err,
+            MmapVecError::FileIo { path, source } => MmapVecError::io_with_path(path, source),
+            MmapVecError::CapacityOverflow { .. } | MmapVecError::SegmentSizeExceeded { .. } => {
+                io::Error::new(io::ErrorKind::InvalidInput, err)
+            }
+            MmapVecError::InsufficientSpace { .. } => {
+                // Same kind as a real `ENOSPC` from the file system.
+                io::Error::new(io::Error::from_raw_os_error(libc::ENOSPC).kind(), err)
+            }
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
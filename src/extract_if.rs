@@ -0,0 +1,95 @@
+use std::ptr;
+
+use crate::Segment;
+
+/// Iterator removing and yielding elements matching a predicate.
+///
+/// Created by `MmapVec::extract_if`.
+///
+/// Elements not yet visited when iterator is drop are kept, so it can be partially
+/// consumed. If it is leaked (e.g. with `mem::forget`), vec is left empty and its
+/// remaining elements are leaked too: nothing is drop twice.
+#[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct ExtractIf<'a, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    segment: &'a mut Segment<T>,
+    /// Index of next element to visit.
+    index: usize,
+    /// Number of elements removed so far.
+    removed: usize,
+    /// Segment len before iteration started.
+    old_len: usize,
+    pred: F,
+}
+
+impl<'a, T, F> ExtractIf<'a, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    pub(crate) fn new(segment: &'a mut Segment<T>, pred: F) -> Self {
+        let old_len = segment.len();
+
+        // Len is fixed when iterator is drop.
+        // Until then, it is safe to leak it: elements would be leaked, not drop twice.
+        unsafe { segment.set_len(0) };
+
+        Self {
+            segment,
+            index: 0,
+            removed: 0,
+            old_len,
+            pred,
+        }
+    }
+}
+
+impl<'a, T, F> Iterator for ExtractIf<'a, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.old_len {
+            unsafe {
+                let current = self.segment.addr.add(self.index);
+                let extracted = (self.pred)(&mut *current);
+                // Only move forward once `pred` returned, so a panic keeps current element.
+                self.index += 1;
+
+                if extracted {
+                    self.removed += 1;
+                    return Some(ptr::read(current));
+                }
+                if self.removed > 0 {
+                    let dst = current.sub(self.removed);
+                    ptr::copy_nonoverlapping(current, dst, 1);
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.old_len - self.index))
+    }
+}
+
+impl<'a, T, F> Drop for ExtractIf<'a, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    fn drop(&mut self) {
+        unsafe {
+            // Shift elements not visited yet over removed ones.
+            if self.index < self.old_len && self.removed > 0 {
+                let src = self.segment.addr.add(self.index);
+                ptr::copy(src, src.sub(self.removed), self.old_len - self.index);
+            }
+            self.segment.set_len(self.old_len - self.removed);
+        }
+    }
+}
@@ -0,0 +1,527 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    os::fd::AsRawFd,
+    path::Path,
+};
+
+/// Low level FS / memory mapping calls used by this crate.
+///
+/// Having them behind a trait allows tests to inject failures.
+pub(crate) trait FsOps {
+    /// Open file in read / write mode, creating it if needed.
+    fn open(path: &Path) -> io::Result<File>;
+
+    /// Resize file to `size` bytes.
+    unsafe fn ftruncate(file: &File, size: usize) -> io::Result<()>;
+
+    /// Map `size` bytes of file in shared read / write mode.
+    unsafe fn mmap(file: &File, size: usize) -> io::Result<*mut libc::c_void>;
+
+    /// Unmap `size` bytes at `addr`.
+    unsafe fn munmap(addr: *mut libc::c_void, size: usize) -> io::Result<()>;
+
+    /// Resize mapping at `addr` from `old_size` to `new_size` bytes, moving it if needed.
+    #[cfg(target_os = "linux")]
+    unsafe fn mremap(
+        addr: *mut libc::c_void,
+        old_size: usize,
+        new_size: usize,
+    ) -> io::Result<*mut libc::c_void>;
+
+    /// Remove file from FS.
+    fn remove_file(path: &Path) -> io::Result<()>;
+
+    /// Rename file `from` to `to`.
+    fn rename(from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Deallocate `len` bytes of file blocks starting at `offset`, keeping file size.
+    ///
+    /// Fails with `EOPNOTSUPP` where hole punching is not supported.
+    fn punch_hole(file: &File, offset: usize, len: usize) -> io::Result<()>;
+}
+
+/// Implementation calling directly `std::fs` and `libc`.
+pub(crate) struct LibcFs;
+
+impl FsOps for LibcFs {
+    fn open(path: &Path) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+    }
+
+    unsafe fn ftruncate(file: &File, size: usize) -> io::Result<()> {
+        if libc::ftruncate(file.as_raw_fd(), size as libc::off_t) != 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    unsafe fn mmap(file: &File, size: usize) -> io::Result<*mut libc::c_void> {
+        // It is safe to not keep a reference to the initial file descriptor.
+        // See: https://stackoverflow.com/questions/17490033/do-i-need-to-keep-a-file-open-after-calling-mmap-on-it
+        let addr = libc::mmap(
+            std::ptr::null_mut(),
+            size as libc::size_t,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        );
+
+        if addr == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(addr)
+        }
+    }
+
+    unsafe fn munmap(addr: *mut libc::c_void, size: usize) -> io::Result<()> {
+        if libc::munmap(addr, size) != 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    unsafe fn mremap(
+        addr: *mut libc::c_void,
+        old_size: usize,
+        new_size: usize,
+    ) -> io::Result<*mut libc::c_void> {
+        let new_addr = libc::mremap(addr, old_size, new_size, libc::MREMAP_MAYMOVE);
+        if new_addr == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(new_addr)
+        }
+    }
+
+    fn remove_file(path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn rename(from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn punch_hole(file: &File, offset: usize, len: usize) -> io::Result<()> {
+        let code = unsafe {
+            libc::fallocate(
+                file.as_raw_fd(),
+                libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                offset as libc::off_t,
+                len as libc::off_t,
+            )
+        };
+        if code != 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn punch_hole(_file: &File, _offset: usize, _len: usize) -> io::Result<()> {
+        Err(io::Error::from_raw_os_error(libc::EOPNOTSUPP))
+    }
+}
+
+/// FS implementation used by the crate.
+#[cfg(not(test))]
+pub(crate) type Fs = LibcFs;
+
+/// FS implementation used by the crate.
+#[cfg(test)]
+pub(crate) type Fs = mock::MockFs;
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::{cell::RefCell, fs::File, io, path::Path};
+
+    use super::{FsOps, LibcFs};
+
+    /// Operations that can be made to fail.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum Op {
+        Open,
+        Ftruncate,
+        Mmap,
+        Munmap,
+        #[cfg(target_os = "linux")]
+        Mremap,
+        RemoveFile,
+        Rename,
+        PunchHole,
+    }
+
+    thread_local! {
+        static FAILURES: RefCell<Vec<(Op, i32)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Make next call to `op` on current thread fail with `errno`.
+    pub(crate) fn inject_failure(op: Op, errno: i32) {
+        FAILURES.with(|failures| failures.borrow_mut().push((op, errno)));
+    }
+
+    fn take_failure(op: Op) -> io::Result<()> {
+        FAILURES.with(|failures| {
+            let mut failures = failures.borrow_mut();
+            match failures.iter().position(|(x, _)| *x == op) {
+                Some(index) => Err(io::Error::from_raw_os_error(failures.remove(index).1)),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Same as `LibcFs` unless a failure has been injected.
+    pub(crate) struct MockFs;
+
+    impl FsOps for MockFs {
+        fn open(path: &Path) -> io::Result<File> {
+            take_failure(Op::Open)?;
+            LibcFs::open(path)
+        }
+
+        unsafe fn ftruncate(file: &File, size: usize) -> io::Result<()> {
+            take_failure(Op::Ftruncate)?;
+            LibcFs::ftruncate(file, size)
+        }
+
+        unsafe fn mmap(file: &File, size: usize) -> io::Result<*mut libc::c_void> {
+            take_failure(Op::Mmap)?;
+            LibcFs::mmap(file, size)
+        }
+
+        unsafe fn munmap(addr: *mut libc::c_void, size: usize) -> io::Result<()> {
+            take_failure(Op::Munmap)?;
+            LibcFs::munmap(addr, size)
+        }
+
+        #[cfg(target_os = "linux")]
+        unsafe fn mremap(
+            addr: *mut libc::c_void,
+            old_size: usize,
+            new_size: usize,
+        ) -> io::Result<*mut libc::c_void> {
+            take_failure(Op::Mremap)?;
+            LibcFs::mremap(addr, old_size, new_size)
+        }
+
+        fn remove_file(path: &Path) -> io::Result<()> {
+            take_failure(Op::RemoveFile)?;
+            LibcFs::remove_file(path)
+        }
+
+        fn rename(from: &Path, to: &Path) -> io::Result<()> {
+            take_failure(Op::Rename)?;
+            LibcFs::rename(from, to)
+        }
+
+        fn punch_hole(file: &File, offset: usize, len: usize) -> io::Result<()> {
+            take_failure(Op::PunchHole)?;
+            LibcFs::punch_hole(file, offset, len)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+    };
+
+    use super::mock::{inject_failure, Op};
+    use crate::{
+        stats::{COUNT_FTRUNCATE_FAILED, COUNT_MMAP_FAILED, COUNT_MUNMAP_FAILED},
+        MmapVec, Segment,
+    };
+
+    struct Droppable(Arc<AtomicU32>);
+
+    impl Drop for Droppable {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_open_failure() {
+        let path = PathBuf::from("test_fs_ops_open_failure.seg");
+
+        inject_failure(Op::Open, libc::EACCES);
+        let err = Segment::<u64>::open_rw(&path, 10).expect_err("open should fail");
+        assert_eq!(err.raw_os_error(), None);
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_ftruncate_failure() {
+        let path = PathBuf::from("test_fs_ops_ftruncate_failure.seg");
+        let failed = COUNT_FTRUNCATE_FAILED.load(Ordering::Relaxed);
+
+        inject_failure(Op::Ftruncate, libc::ENOSPC);
+        let err = Segment::<u64>::open_rw(&path, 10).expect_err("open should fail");
+        assert!(err
+            .to_string()
+            .contains("test_fs_ops_ftruncate_failure.seg"));
+        assert!(COUNT_FTRUNCATE_FAILED.load(Ordering::Relaxed) > failed);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_reserve_failure_keeps_vec_consistent() {
+        let mut v = MmapVec::<u64>::try_from([1, 2, 3]).expect("fail to create vec");
+        let capacity = v.capacity();
+        let failed = COUNT_MMAP_FAILED.load(Ordering::Relaxed);
+
+        #[cfg(target_os = "linux")]
+        inject_failure(Op::Mremap, libc::ENOMEM);
+        inject_failure(Op::Mmap, libc::ENOMEM);
+        assert!(v.reserve(capacity * 4).is_err());
+        assert!(COUNT_MMAP_FAILED.load(Ordering::Relaxed) > failed);
+
+        // Vec is still usable.
+        assert_eq!(v.capacity(), capacity);
+        assert_eq!(&v[..], [1, 2, 3]);
+
+        v.reserve(capacity * 4).expect("fail to reserve");
+        assert_eq!(&v[..], [1, 2, 3]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_mremap_failure_maps_again() {
+        let mut v = MmapVec::<u64>::try_from([1, 2, 3]).expect("fail to create vec");
+        let capacity = v.capacity();
+
+        inject_failure(Op::Mremap, libc::ENOMEM);
+        v.reserve(capacity * 4).expect("fail to reserve");
+        assert!(v.capacity() > capacity);
+        assert_eq!(&v[..], [1, 2, 3]);
+        assert_eq!(
+            std::fs::metadata(v.path()).expect("no file").len(),
+            v.disk_size() as u64
+        );
+
+        // Next grow uses mremap again
+        let capacity = v.capacity();
+        v.reserve(capacity * 4).expect("fail to reserve");
+        assert_eq!(&v[..], [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_reserve_no_space_keeps_vec_intact() {
+        let counter = Arc::new(AtomicU32::new(0));
+        let mut v = MmapVec::<Droppable>::new();
+
+        // Fail from null segment
+        inject_failure(Op::Ftruncate, libc::ENOSPC);
+        let err = v.reserve(10).expect_err("reserve should fail");
+        assert_eq!(err.raw_os_error(), None);
+        assert_eq!(v.len(), 0);
+        assert_eq!(v.capacity(), 0);
+
+        for _ in 0..3 {
+            v.push(Droppable(counter.clone())).expect("fail to push");
+        }
+        let capacity = v.capacity();
+
+        // Fail to grow existing segment
+        inject_failure(Op::Ftruncate, libc::ENOSPC);
+        assert!(v.reserve(capacity * 4).is_err());
+        inject_failure(Op::Ftruncate, libc::ENOSPC);
+        assert!(v.reserve_exact(capacity * 4).is_err());
+
+        // Nothing was dropped and vec is still usable.
+        assert_eq!(counter.load(Ordering::Relaxed), 0);
+        assert_eq!(v.len(), 3);
+        assert_eq!(v.capacity(), capacity);
+        assert_eq!(
+            std::fs::metadata(v.path()).expect("no file").len(),
+            v.disk_size() as u64
+        );
+
+        v.reserve(capacity * 4).expect("fail to reserve");
+        assert!(v.capacity() > capacity);
+        assert_eq!(v.len(), 3);
+        assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+        drop(v);
+        assert_eq!(counter.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_shrink_to_fit_failure_restores_file() {
+        let mut v = MmapVec::<u64>::with_capacity(10_000).expect("fail to create vec");
+        for x in [1, 2, 3] {
+            v.push(x).expect("fail to push");
+        }
+        let capacity = v.capacity();
+
+        inject_failure(Op::Mmap, libc::ENOMEM);
+        assert!(v.shrink_to_fit().is_err());
+
+        // Current mapping is still fully backed by the file.
+        assert_eq!(v.capacity(), capacity);
+        assert_eq!(
+            std::fs::metadata(v.path()).expect("no file").len(),
+            v.disk_size() as u64
+        );
+        v.resize(capacity, 4).expect("fail to resize");
+        assert_eq!(&v[..3], [1, 2, 3]);
+        assert_eq!(v[capacity - 1], 4);
+    }
+
+    #[test]
+    fn test_into_chunk_files_failure_gives_source_back() {
+        let v = MmapVec::<u64>::try_from([1, 2, 3, 4, 5]).expect("fail to create vec");
+        let path = v.path();
+
+        inject_failure(Op::Ftruncate, libc::ENOSPC);
+        let (v, err) = v.into_chunk_files(2).expect_err("chunking should fail");
+        assert!(err.to_string().contains("No space left"));
+
+        // Source vec and its file are untouched.
+        assert_eq!(&v[..], [1, 2, 3, 4, 5]);
+        assert_eq!(v.path(), path);
+        assert!(path.exists());
+
+        let chunks = v.into_chunk_files(2).expect("fail to split vec");
+        assert_eq!(chunks.len(), 3);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_retry_on_eintr() {
+        let path = PathBuf::from("test_fs_ops_retry_on_eintr.seg");
+
+        inject_failure(Op::Ftruncate, libc::EINTR);
+        inject_failure(Op::Mmap, libc::EINTR);
+        inject_failure(Op::Mmap, libc::EINTR);
+        let mut segment = Segment::<u64>::open_rw(&path, 10).expect("fail to open segment");
+        assert_eq!(segment.push_within_capacity(42), Ok(()));
+        assert_eq!(&segment[..], [42]);
+
+        inject_failure(Op::Munmap, libc::EINTR);
+        drop(segment);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_munmap_failure() {
+        let path = PathBuf::from("test_fs_ops_munmap_failure.seg");
+        let failed = COUNT_MUNMAP_FAILED.load(Ordering::Relaxed);
+
+        let segment = Segment::<u64>::open_rw(&path, 10).expect("fail to open segment");
+        inject_failure(Op::Munmap, libc::EINVAL);
+        drop(segment);
+        assert!(COUNT_MUNMAP_FAILED.load(Ordering::Relaxed) > failed);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_remove_file_failure() {
+        let v = MmapVec::<u64>::try_from([1, 2, 3]).expect("fail to create vec");
+        let path = v.path();
+
+        inject_failure(Op::RemoveFile, libc::EBUSY);
+        drop(v);
+        assert!(path.exists());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_reserve_via_copy_failure() {
+        let mut v = MmapVec::<u64>::try_from([1, 2, 3]).expect("fail to create vec");
+        let capacity = v.capacity();
+        let tmp_path = v.path().with_extension("grow");
+
+        // Fail to create new segment
+        inject_failure(Op::Mmap, libc::ENOMEM);
+        assert!(v.reserve_via_copy(capacity * 4).is_err());
+        assert!(!tmp_path.exists());
+
+        // Fail to replace current file
+        inject_failure(Op::Rename, libc::EACCES);
+        assert!(v.reserve_via_copy(capacity * 4).is_err());
+        assert!(!tmp_path.exists());
+
+        // Vec is still usable.
+        assert_eq!(v.capacity(), capacity);
+        assert_eq!(&v[..], [1, 2, 3]);
+        assert_eq!(
+            std::fs::metadata(v.path()).expect("no file").len(),
+            v.disk_size() as u64
+        );
+
+        v.reserve_via_copy(capacity * 4).expect("fail to reserve");
+        assert_eq!(&v[..], [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_truncate_and_punch_unsupported() {
+        let mut v = MmapVec::<u64>::new();
+        for i in 0..10_000 {
+            v.push(i).expect("fail to push");
+        }
+        let capacity = v.capacity();
+
+        // Fallback to madvise
+        inject_failure(Op::PunchHole, libc::EOPNOTSUPP);
+        v.truncate_and_punch(10).expect("fail to truncate");
+        assert_eq!(v.len(), 10);
+        assert_eq!(v.capacity(), capacity);
+        assert!(v.iter().copied().eq(0..10));
+
+        // Other errors are returned, but vec is still truncated.
+        inject_failure(Op::PunchHole, libc::EIO);
+        assert!(v.truncate_and_punch(5).is_err());
+        assert_eq!(v.len(), 5);
+        assert!(v.iter().copied().eq(0..5));
+    }
+
+    #[test]
+    fn test_move_to_dir_cross_device() {
+        let dir = std::env::temp_dir().join("test_fs_ops_move_to_dir_cross_device");
+        std::fs::create_dir_all(&dir).expect("fail to create dir");
+
+        let mut v = MmapVec::<u64>::try_from([1, 2, 3]).expect("fail to create vec");
+        let old_path = v.path();
+
+        inject_failure(Op::Rename, libc::EXDEV);
+        v.move_to_dir(&dir).expect("fail to move vec");
+
+        assert!(v.path().starts_with(&dir));
+        assert!(v.path().exists());
+        assert!(!old_path.exists());
+        assert_eq!(&v[..], [1, 2, 3]);
+
+        v.push(4).expect("fail to push");
+        assert_eq!(&v[..], [1, 2, 3, 4]);
+
+        // Other errors are returned.
+        inject_failure(Op::Rename, libc::EACCES);
+        assert!(v
+            .move_to_dir(old_path.parent().expect("no parent"))
+            .is_err());
+        assert!(v.path().starts_with(&dir));
+        assert_eq!(&v[..], [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,76 @@
+use std::{iter::FusedIterator, ptr};
+
+use crate::{DefaultSegmentBuilder, MmapVec, SegmentBuilder};
+
+/// Iterator moving values out of a vec.
+///
+/// Created by `MmapVec::into_iter`.
+///
+/// Iterator owns the vec: values not yielded yet are drop with it, and backing file is
+/// then removed, unless vec is persistent (see `MmapVec::set_persistent`).
+#[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct IntoIter<T, B: SegmentBuilder = DefaultSegmentBuilder> {
+    vec: MmapVec<T, B>,
+    /// Index of next value to yield.
+    index: usize,
+    /// Vec len before iteration started.
+    len: usize,
+}
+
+impl<T, B> IntoIter<T, B>
+where
+    B: SegmentBuilder,
+{
+    pub(crate) fn new(mut vec: MmapVec<T, B>) -> Self {
+        let len = vec.len();
+
+        // Values are now owned by the iterator, vec must not drop them.
+        unsafe { vec.segment.set_len(0) };
+
+        Self { vec, index: 0, len }
+    }
+}
+
+impl<T, B> Iterator for IntoIter<T, B>
+where
+    B: SegmentBuilder,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            return None;
+        }
+
+        let value = unsafe { ptr::read(self.vec.segment.addr.add(self.index)) };
+        self.index += 1;
+        Some(value)
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, B> ExactSizeIterator for IntoIter<T, B> where B: SegmentBuilder {}
+
+impl<T, B> FusedIterator for IntoIter<T, B> where B: SegmentBuilder {}
+
+impl<T, B> Drop for IntoIter<T, B>
+where
+    B: SegmentBuilder,
+{
+    fn drop(&mut self) {
+        if self.index < self.len {
+            unsafe {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                    self.vec.segment.addr.add(self.index),
+                    self.len - self.index,
+                ));
+            }
+        }
+    }
+}
@@ -114,19 +114,42 @@ Prefetching API is not fully stable for now and may change in the future.
  */
 
 use std::{
-    fs, io, mem,
-    ops::{Deref, DerefMut},
-    path::PathBuf,
+    fs::{self, File},
+    io::{self, Read, Write},
+    mem,
+    ops::{Bound, Deref, DerefMut, Range, RangeBounds},
+    path::{Path, PathBuf},
+    ptr, slice,
+    sync::atomic::Ordering,
 };
 
 #[cfg(feature = "serde")]
 use std::marker::PhantomData;
 
+#[cfg(feature = "background-flush")]
+use std::time::Duration;
+
+#[cfg(feature = "background-flush")]
+use background_flush::BackgroundFlush;
+
+pub use advice::AccessPattern;
+pub use buffered_vec::BufferedMmapVec;
+pub use chunked_vec::ChunkedMmapVec;
+pub use concurrent_vec::ConcurrentMmapVec;
+pub use error::MmapVecError;
+pub use extract_if::ExtractIf;
+pub use into_iter::IntoIter;
+pub use numeric::Numeric;
+pub use pod::Pod;
+pub use prefetch_iter::IndexedPrefetchIter;
 pub use segment::Segment;
 pub use segment_builder::{DefaultSegmentBuilder, SegmentBuilder};
+pub use shared_vec::SharedMmapVec;
 pub use stats::MmapStats;
 use utils::check_zst;
+pub use utils::{files_equal, round_capacity_to_page};
 pub use vec_builder::MmapVecBuilder;
+use vec_builder::VecConfig;
 
 #[cfg(feature = "serde")]
 use serde::{
@@ -135,20 +158,85 @@ use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
 };
 
-use crate::utils::page_size;
+use crate::{
+    fs_ops::{Fs, FsOps},
+    stats::COUNT_VEC_GROW,
+    utils::page_size,
+};
 
+/// Number of pages prefetched ahead when scanning vec.
+const PREFETCH_AHEAD_PAGES: usize = 16;
+
+/// Transparent huge page size, also used as threshold to start using them.
+const HUGE_PAGE_SIZE: usize = 2 << 20;
+
+/// Magic bytes at beginning of files written by `MmapVec::save`.
+const SAVE_MAGIC: [u8; 8] = *b"MMAPVEC\0";
+
+/// Version of `MmapVec::save` file format.
+const SAVE_VERSION: u64 = 1;
+
+/// Size of `MmapVec::save` file header: magic, version, element size and len.
+const SAVE_HEADER_SIZE: usize = 32;
+
+mod advice;
+#[cfg(feature = "background-flush")]
+mod background_flush;
+mod buffered_vec;
+mod chunked_vec;
+mod concurrent_vec;
+mod error;
+mod extract_if;
+mod fs_ops;
+mod into_iter;
+mod numeric;
+mod pod;
+mod prefetch_iter;
 mod segment;
 mod segment_builder;
+mod shared_vec;
 mod stats;
 mod utils;
 mod vec_builder;
 
 /// A disk memory mapped vector.
+///
+/// # Remapping and borrowed slices
+///
+/// Like `Vec` reallocation, growing the vec (`push`, `reserve`, `resize`, ...) may remap
+/// its segment at a new address, and the old mapping is then released.
+/// Slices, iterators and references obtained through `Deref` / `DerefMut` borrow the vec,
+/// so the borrow checker rejects any growing call while they are alive:
+///
+/// ```compile_fail
+/// # use mmap_vec::MmapVec;
+/// let mut v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+///
+/// for x in v.iter_mut() {
+///     // Cannot borrow `v` as mutable more than once at a time.
+///     v.push(*x).unwrap();
+/// }
+/// ```
+///
+/// Raw pointers (from `as_ptr`, `as_mut_ptr`, ...) are not tracked: they are invalidated
+/// by any call that can remap the segment (growing calls and `move_to_dir`), and using
+/// them afterward is undefined behavior. Reserve enough room up front when such pointers
+/// are shared with unsafe / FFI code.
+///
+/// No runtime guard is done here: it would require wrapping every borrowed slice.
 #[derive(Debug)]
 pub struct MmapVec<T, B: SegmentBuilder = DefaultSegmentBuilder> {
     pub(crate) segment: Segment<T>,
     pub(crate) builder: B,
     pub(crate) path: PathBuf,
+    /// Keep backing file when vec is drop.
+    pub(crate) persistent: bool,
+    /// Number of times segment has been remapped to grow.
+    pub(crate) grows: u64,
+    /// Settings chosen with `MmapVecBuilder`.
+    pub(crate) config: VecConfig,
+    #[cfg(feature = "background-flush")]
+    pub(crate) background_flush: Option<BackgroundFlush>,
 }
 
 impl<T, B> MmapVec<T, B>
@@ -158,173 +246,1974 @@ where
     /// Create a zero size mmap vec.
     #[inline(always)]
     pub fn new() -> Self {
+        Self::with_builder(B::default())
+    }
+
+    /// Create a zero size mmap vec using given segment builder.
+    ///
+    /// Like `new`, no IO is done here: segment file is created on first grow.
+    /// If builder folder is missing at that time, grow fails with a
+    /// `MmapVecError::InvalidStoreDir` naming it.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::{DefaultSegmentBuilder, MmapVec};
+    /// let builder = DefaultSegmentBuilder::with_path("/tmp/mmap-vec-with-builder");
+    /// builder.create_dir_all().unwrap();
+    ///
+    /// let mut v = MmapVec::<u8, _>::with_builder(builder);
+    /// assert_eq!(v.capacity(), 0);
+    /// assert!(v.path().starts_with("/tmp/mmap-vec-with-builder"));
+    ///
+    /// v.push(42).unwrap();
+    /// assert!(v.path().exists());
+    /// ```
+    #[inline(always)]
+    pub fn with_builder(builder: B) -> Self {
         check_zst::<T>();
 
-        let builder = B::default();
-        let path = builder.new_segment_path();
-        Self {
-            segment: Segment::null(),
-            builder,
-            path,
+        let path = builder.new_segment_path();
+        Self {
+            segment: Segment::null(),
+            builder,
+            path,
+            persistent: false,
+            grows: 0,
+            config: VecConfig::default(),
+            #[cfg(feature = "background-flush")]
+            background_flush: None,
+        }
+    }
+
+    /// Wrap an existing segment into a managed vec.
+    ///
+    /// Vec takes ownership of both the segment and its file: the file will be removed
+    /// when the vec is drop. Since `Segment` never removes its file, it cannot be
+    /// unlinked twice as long as nothing else owns `path`.
+    ///
+    /// # Safety
+    ///
+    /// `segment` must have been mapped from `path` (e.g. using `Segment::open_rw`),
+    /// and `path` must not be owned by any other vec.
+    /// Otherwise data may be lost when vec grows.
+    #[inline(always)]
+    pub unsafe fn from_segment(segment: Segment<T>, builder: B, path: PathBuf) -> Self {
+        Self {
+            segment,
+            builder,
+            path,
+            persistent: false,
+            grows: 0,
+            config: VecConfig::default(),
+            #[cfg(feature = "background-flush")]
+            background_flush: None,
+        }
+    }
+
+    /// Create a mmap vec with a given capacity.
+    ///
+    /// This function can fail if FS / IO failed.
+    #[inline(always)]
+    pub fn with_capacity(capacity: usize) -> io::Result<Self> {
+        MmapVecBuilder::new().capacity(capacity).try_build()
+    }
+
+    /// Create a vec with `capacity` at a new path from `builder`, mapped using `config`.
+    ///
+    /// This is used by `MmapVecBuilder::try_build`, and by every function deriving a new
+    /// vec from an existing one, so settings are applied to its first mapping like
+    /// on a grow (free space check, guard page, `madvise` flags, poisoning).
+    pub(crate) fn open_with_config(
+        builder: B,
+        capacity: usize,
+        config: VecConfig,
+    ) -> io::Result<Self> {
+        check_segment_size_limit::<T>(capacity, config.segment_size_limit)?;
+
+        let mut vec = Self::with_builder(builder);
+        vec.config = config;
+        if capacity == 0 {
+            return Ok(vec);
+        }
+
+        vec.segment = vec.open_segment(capacity)?;
+        if let Some(byte) = config.poison_spare {
+            vec.segment.poison_from(0, byte);
+        }
+        vec.advise_mapping();
+        Ok(vec)
+    }
+
+    /// Capacity of a vec cloned from this one.
+    ///
+    /// Empty vecs are cloned into a null segment, so no file is created for them.
+    #[inline(always)]
+    fn clone_capacity(&self) -> usize {
+        if self.is_empty() {
+            0
+        } else {
+            self.capacity()
+        }
+    }
+
+    /// Currently used vec size.
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.segment.capacity()
+    }
+
+    /// Bytes use on disk for this vec.
+    #[inline(always)]
+    pub fn disk_size(&self) -> usize {
+        self.segment.disk_size()
+    }
+
+    /// Size in bytes of a single vec element.
+    #[inline(always)]
+    pub fn element_size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+
+    /// Alignment in bytes of vec elements.
+    #[inline(always)]
+    pub fn element_align(&self) -> usize {
+        mem::align_of::<T>()
+    }
+
+    /// Index of the element `elem` points to, or `None` if it is not a reference into this vec.
+    ///
+    /// This relies on pointer identity, not on value equality: an equal value stored
+    /// elsewhere gives `None`. Use `iter().position()` to search by value.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u32>::try_from([4, 7, 4]).unwrap();
+    /// let elem = v.iter().rev().find(|x| **x == 4).unwrap();
+    /// assert_eq!(v.index_of(elem), Some(2));
+    /// assert_eq!(v.index_of(&4), None);
+    /// ```
+    pub fn index_of(&self, elem: &T) -> Option<usize> {
+        let start = self.as_ptr() as usize;
+        let offset = (elem as *const T as usize).checked_sub(start)?;
+        let index = offset / mem::size_of::<T>();
+        (offset % mem::size_of::<T>() == 0 && index < self.len()).then_some(index)
+    }
+
+    /// Max segment file size in bytes, if any.
+    ///
+    /// See `MmapVecBuilder::segment_size_limit` for more details.
+    #[inline(always)]
+    pub fn segment_size_limit(&self) -> Option<usize> {
+        self.config.segment_size_limit
+    }
+
+    /// Shortens the vec, keeping the first `new_len` elements and dropping
+    /// the rest.
+    #[inline(always)]
+    pub fn truncate(&mut self, new_len: usize) {
+        self.segment.truncate(new_len);
+    }
+
+    /// Declare that the first `len` elements of the vec are initialized.
+    ///
+    /// This is meant for vec whose backing file already holds meaningful data, like a
+    /// reopened segment without any header: after `with_capacity` or `from_segment`,
+    /// caller tells how many elements are valid.
+    ///
+    /// Will panic if `len` is greater than capacity.
+    ///
+    /// # Safety
+    ///
+    /// First `len` elements must hold valid values of `T`. If `len` is smaller than
+    /// current len, elements after it are not dropped.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u64>::with_capacity(10).unwrap();
+    /// // New segment files are zero filled.
+    /// unsafe { v.assume_init_len(3) };
+    /// assert_eq!(&v[..], [0, 0, 0]);
+    /// ```
+    pub unsafe fn assume_init_len(&mut self, len: usize) {
+        let capacity = self.capacity();
+        assert!(
+            len <= capacity,
+            "initialized len (is {len}) should be <= capacity (is {capacity})"
+        );
+        self.segment.set_len(len);
+    }
+
+    /// Same as `truncate`, then give disk blocks after new len back to the file system.
+    ///
+    /// Whole pages past the last kept element are deallocated with
+    /// `fallocate(FALLOC_FL_PUNCH_HOLE)`. File size and capacity are kept, so next
+    /// pushes do not need to remap the segment: holes are filled with zeros and
+    /// allocated again on first write.
+    ///
+    /// Hole punching is only available on Linux, and only on some file systems
+    /// (ext4, xfs, btrfs, tmpfs, ...). Elsewhere, pages are only advised as free
+    /// like `advice_free_range` does, so RAM is reclaimed but disk is not.
+    ///
+    /// Elements are dropped even if this function returns an error.
+    pub fn truncate_and_punch(&mut self, new_len: usize) -> io::Result<()> {
+        self.truncate(new_len);
+
+        let page_size = page_size();
+        let start = (self.len() * mem::size_of::<T>() + page_size - 1) / page_size * page_size;
+        let end = self.disk_size();
+        if start >= end {
+            return Ok(());
+        }
+
+        let file = Fs::open(&self.path)?;
+        match Fs::punch_hole(&file, start, end - start) {
+            Err(err) if err.raw_os_error() == Some(libc::EOPNOTSUPP) => {
+                self.advice_free_range(self.len()..self.capacity());
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Resizes the vec so its len is exactly `new_len`.
+    ///
+    /// If `new_len` is greater than current len, the vec is extended by clones of `value`.
+    /// Otherwise, the vec is truncated like calling `truncate`.
+    ///
+    /// Unlike `Segment::set_len` (which only force the len and is unsafe),
+    /// every element is always initialized or dropped.
+    ///
+    /// This function can fail if vec needs to grow, because it depends on FS / IO calls.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u8>::try_from([1, 2]).unwrap();
+    ///
+    /// v.resize(4, 9).unwrap();
+    /// assert_eq!(&v[..], [1, 2, 9, 9]);
+    ///
+    /// v.resize(1, 9).unwrap();
+    /// assert_eq!(&v[..], [1]);
+    /// ```
+    pub fn resize(&mut self, new_len: usize, value: T) -> io::Result<()>
+    where
+        T: Clone,
+    {
+        let len = self.len();
+        if new_len <= len {
+            self.truncate(new_len);
+            return Ok(());
+        }
+
+        self.reserve(new_len - len)?;
+        for _ in len + 1..new_len {
+            if self.push_within_capacity(value.clone()).is_err() {
+                panic_bad_capacity();
+            }
+        }
+        if self.push_within_capacity(value).is_err() {
+            panic_bad_capacity();
+        }
+
+        Ok(())
+    }
+
+    /// Make the vec exactly `new_len` long, filling new slots with clones of `value`.
+    ///
+    /// This is the same operation as `resize`, named after what it does to the len.
+    /// It is always safe, unlike `Segment::set_len`.
+    #[inline(always)]
+    pub fn set_len_filled(&mut self, new_len: usize, value: T) -> io::Result<()>
+    where
+        T: Clone,
+    {
+        self.resize(new_len, value)
+    }
+
+    /// Remove `delete_count` element at beginning of the vec.
+    ///
+    /// Element will be drop in place.
+    ///
+    /// If delete count is greater than the segment len, then this call will be
+    /// equivalent to calling `clear` function.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u8>::new();
+    /// assert!(v.push(8).is_ok());
+    /// assert!(v.push(5).is_ok());
+    /// assert!(v.push(3).is_ok());
+    /// assert!(v.push(12).is_ok());
+    /// assert_eq!(&v[..], &[8, 5, 3, 12]);
+    ///
+    /// assert_eq!(v.truncate_first(2), 2);
+    /// assert_eq!(&v[..], [3, 12]);
+    ///
+    /// assert_eq!(v.truncate_first(100), 2);
+    /// assert_eq!(&v[..], []);
+    /// ```
+    #[inline(always)]
+    pub fn truncate_first(&mut self, delete_count: usize) -> usize {
+        self.segment.truncate_first(delete_count)
+    }
+
+    /// Removes all but the first of consecutive elements in the vec satisfying
+    /// a given equality relation.
+    ///
+    /// Same semantic as `Vec::dedup_by`, useful to dedup sorted streams
+    /// on a custom key.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<(u32, &str)>::new();
+    /// assert!(v.push((1, "a")).is_ok());
+    /// assert!(v.push((1, "b")).is_ok());
+    /// assert!(v.push((2, "c")).is_ok());
+    /// assert!(v.push((3, "d")).is_ok());
+    /// assert!(v.push((3, "e")).is_ok());
+    ///
+    /// v.dedup_by(|a, b| a.0 == b.0);
+    /// assert_eq!(&v[..], [(1, "a"), (2, "c"), (3, "d")]);
+    /// ```
+    #[inline(always)]
+    pub fn dedup_by<F>(&mut self, same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        self.segment.dedup_by(same_bucket);
+    }
+
+    /// Removes consecutive repeated elements, and return length of every run.
+    ///
+    /// Output contains one `(index, run_len)` pair per kept element, `index` being
+    /// its position in the deduplicated vec. On sorted data, this gives frequency
+    /// of every distinct value in a single pass.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u8>::try_from([1, 1, 1, 4, 5, 5]).unwrap();
+    ///
+    /// assert_eq!(v.dedup_with_counts(), [(0, 3), (1, 1), (2, 2)]);
+    /// assert_eq!(&v[..], [1, 4, 5]);
+    /// ```
+    pub fn dedup_with_counts(&mut self) -> Vec<(usize, usize)>
+    where
+        T: PartialEq,
+    {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut runs = vec![(0, 1)];
+        self.segment.dedup_by(|a, b| {
+            if a == b {
+                if let Some(run) = runs.last_mut() {
+                    run.1 += 1;
+                }
+                true
+            } else {
+                runs.push((runs.len(), 1));
+                false
+            }
+        });
+        runs
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// Same semantic as `Vec::retain`: order of retained elements is preserved.
+    /// Predicate visits every element exactly once, in original order.
+    /// Kept elements are shifted towards beginning of the vec rather than swapped
+    /// with removed ones, so sorted data stays sorted.
+    /// Pages are prefetched ahead while scanning, so it also performs well on cold data.
+    ///
+    /// Capacity is left unchanged, see `retain_and_shrink` to also reclaim disk space.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u32>::try_from([1, 2, 3, 5, 8, 13]).unwrap();
+    /// v.retain(|x| x % 2 == 1);
+    /// assert_eq!(&v[..], [1, 3, 5, 13]);
+    /// ```
+    #[inline(always)]
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.segment.retain(f);
+    }
+
+    /// Retains only elements at given indexes, dropping all others.
+    ///
+    /// `keep` must be sorted and deduplicated. Retained elements are compacted at
+    /// beginning of the vec, keeping their order. This avoids calling a predicate
+    /// when the set of rows to keep is already known (e.g. from a secondary index).
+    ///
+    /// Will panic if `keep` is not sorted and deduplicated, or if an index is out of
+    /// bounds, before any element is removed.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u8>::try_from([8, 5, 3, 12, 7]).unwrap();
+    /// v.retain_indices(&[1, 3, 4]);
+    /// assert_eq!(&v[..], [5, 12, 7]);
+    /// ```
+    pub fn retain_indices(&mut self, keep: &[usize]) {
+        assert!(
+            keep.windows(2).all(|w| w[0] < w[1]),
+            "Indexes to keep must be sorted and deduplicated"
+        );
+        if let Some(&last) = keep.last() {
+            let len = self.len();
+            assert!(
+                last < len,
+                "index out of bounds: the len is {len} but the index is {last}"
+            );
+        }
+
+        let mut keep = keep.iter().peekable();
+        let mut index = 0;
+        self.segment.retain(|_| {
+            let retained = keep.next_if_eq(&&index).is_some();
+            index += 1;
+            retained
+        });
+    }
+
+    /// Creates an iterator removing elements matching `pred`, and yielding them by value.
+    ///
+    /// Same semantic as `Vec::extract_if`: unlike `retain`, removed elements are given back
+    /// instead of being drop, and `pred` can mutate every visited element.
+    /// Kept elements are compacted at beginning of the vec, keeping their order.
+    ///
+    /// If iterator is only partially consumed, elements not visited yet are kept.
+    /// Capacity is left unchanged.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u32>::try_from([1, 2, 3, 5, 8, 13]).unwrap();
+    /// let evens: Vec<_> = v.extract_if(|x| *x % 2 == 0).collect();
+    /// assert_eq!(evens, [2, 8]);
+    /// assert_eq!(&v[..], [1, 3, 5, 13]);
+    /// ```
+    #[inline(always)]
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        ExtractIf::new(&mut self.segment, pred)
+    }
+
+    /// Same as `retain`, then shrink the backing file to fit retained elements.
+    ///
+    /// See `shrink_to_fit` for more details.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u64>::with_capacity(10_000).unwrap();
+    /// for i in 0..10_000 {
+    ///     v.push(i).unwrap();
+    /// }
+    ///
+    /// v.retain_and_shrink(|x| x % 100 == 0).unwrap();
+    /// assert_eq!(v.len(), 100);
+    /// assert_eq!(v.capacity(), mmap_vec::round_capacity_to_page::<u64>(100));
+    /// ```
+    pub fn retain_and_shrink<F>(&mut self, f: F) -> io::Result<()>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain(f);
+        self.shrink_to_fit()
+    }
+
+    /// Clears the vec, removing all values.
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.segment.clear();
+    }
+
+    /// Clears the vec, then tell the kernel that all its pages are not needed anymore.
+    ///
+    /// Capacity and file size are kept, so next pushes do not need to remap the segment,
+    /// while RAM used by dead values can be reclaimed.
+    ///
+    /// See `Segment::advice_free_range` for more details.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    /// let capacity = v.capacity();
+    ///
+    /// v.clear_and_evict();
+    /// assert!(v.is_empty());
+    /// assert_eq!(v.capacity(), capacity);
+    /// ```
+    pub fn clear_and_evict(&mut self) {
+        self.segment.clear();
+        self.segment.advise(AccessPattern::DontNeed);
+    }
+
+    /// Remove last value of the vec.
+    ///
+    /// Value will be return if data structure is not empty.
+    #[inline(always)]
+    pub fn pop(&mut self) -> Option<T> {
+        self.segment.pop()
+    }
+
+    /// Append a value to the vec.
+    ///
+    /// If vec is too small:
+    /// - new segment may be created.
+    /// - current segment may be resize.
+    ///
+    /// This is why this function can fail, because it depends on FS / IO calls.
+    ///
+    /// Capacity at least doubles on each grow (with a minimum of one page), so
+    /// pushing `n` elements only remaps the segment `O(log n)` times.
+    ///
+    /// Vec never shrinks on `pop`, so alternating pop / push around capacity
+    /// boundary does not remap the segment.
+    ///
+    /// When a segment size limit is set, growth stops at the limit and pushing
+    /// into a vec full up to it returns `MmapVecError::SegmentSizeExceeded`.
+    pub fn push(&mut self, value: T) -> Result<(), io::Error> {
+        self.reserve_one()?;
+
+        // Add new value to vec: `reserve_one` guarantees there is room for it.
+        unsafe { self.segment.push_unchecked(value) };
+
+        Ok(())
+    }
+
+    /// Insert `value` at position `index`, shifting all elements after it to the right.
+    ///
+    /// Vec grows like with `push`, so this function can fail.
+    ///
+    /// Will panic if `index > len`.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    /// v.insert(1, 8).unwrap();
+    /// v.insert(4, 9).unwrap();
+    /// assert_eq!(&v[..], [1, 8, 2, 3, 9]);
+    /// ```
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), io::Error> {
+        let len = self.len();
+        assert!(
+            index <= len,
+            "insertion index (is {index}) should be <= len (is {len})"
+        );
+
+        self.reserve_one()?;
+
+        assert!(
+            self.segment.insert_within_capacity(index, value).is_ok(),
+            "Fail to insert to newly created segment"
+        );
+
+        Ok(())
+    }
+
+    /// Insert `value` in a sorted vec, keeping it sorted, and return its index.
+    ///
+    /// Insertion point is found using a binary search. Value is inserted after
+    /// all elements equal to it, so equal elements keep their insertion order.
+    ///
+    /// Vec must be sorted, otherwise insertion point is unspecified.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u8>::try_from([1, 3, 3, 5]).unwrap();
+    /// assert_eq!(v.insert_sorted(3).unwrap(), 3);
+    /// assert_eq!(v.insert_sorted(0).unwrap(), 0);
+    /// assert_eq!(v.insert_sorted(8).unwrap(), 6);
+    /// assert_eq!(&v[..], [0, 1, 3, 3, 3, 5, 8]);
+    /// ```
+    pub fn insert_sorted(&mut self, value: T) -> Result<usize, io::Error>
+    where
+        T: Ord,
+    {
+        let index = self.partition_point(|x| x <= &value);
+        self.insert(index, value)?;
+        Ok(index)
+    }
+
+    /// Same as `push`, but return index of inserted value.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u8>::new();
+    /// assert_eq!(v.push_and_index(8).unwrap(), 0);
+    /// assert_eq!(v.push_and_index(5).unwrap(), 1);
+    /// ```
+    #[inline(always)]
+    pub fn push_and_index(&mut self, value: T) -> Result<usize, io::Error> {
+        self.push(value)?;
+        Ok(self.len() - 1)
+    }
+
+    /// Try to push a new value to the data structure.
+    ///
+    /// If vec is too small, value will be return as an `Err`.
+    #[inline(always)]
+    pub fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
+        self.segment.push_within_capacity(value)
+    }
+
+    /// Push values from `iter` until vec is full, without ever growing it.
+    ///
+    /// First value that does not fit is return as an `Err`, and iterator is not polled
+    /// after it. Pass iterator by reference (e.g. `iter.by_ref()`) to consume remaining
+    /// values later.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u8>::with_capacity(3).unwrap();
+    /// let mut iter = 1..10;
+    ///
+    /// assert_eq!(v.extend_within_capacity(iter.by_ref()), Err(4));
+    /// assert_eq!(&v[..], [1, 2, 3]);
+    /// assert_eq!(iter.next(), Some(5));
+    /// ```
+    pub fn extend_within_capacity<I>(&mut self, iter: I) -> Result<(), T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for value in iter {
+            self.push_within_capacity(value)?;
+        }
+        Ok(())
+    }
+
+    /// Push all values from `iter`, growing the vec as needed, and return how many were pushed.
+    ///
+    /// On IO error, pushing stops: values pushed so far are kept, the value that failed
+    /// to be pushed is dropped and iterator is not polled anymore. Number of values
+    /// pushed is returned along with the error, so bulk ingestion can resume from there.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVecBuilder;
+    /// let mut v = MmapVecBuilder::<u64>::new()
+    ///     .capacity(0)
+    ///     .segment_size_limit(80)
+    ///     .try_build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(v.try_extend(0..4).unwrap(), 4);
+    ///
+    /// let (pushed, err) = v.try_extend(4..20).unwrap_err();
+    /// assert_eq!(pushed, 6);
+    /// assert!(err.to_string().starts_with("segment size exceeded"));
+    /// assert_eq!(v.len(), 10);
+    /// ```
+    pub fn try_extend<I>(&mut self, iter: I) -> Result<usize, (usize, io::Error)>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut pushed = 0;
+        for value in iter {
+            self.push(value).map_err(|err| (pushed, err))?;
+            pushed += 1;
+        }
+        Ok(pushed)
+    }
+
+    /// Append all values from an exact size iterator, reserving room for them once.
+    ///
+    /// Unlike pushing values one by one, segment is remapped at most once.
+    /// If reserve fails, iterator is not polled and the vec is left unchanged.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u32>::try_from([1, 2]).unwrap();
+    /// v.append_exact(3..100_000).unwrap();
+    /// assert_eq!(v.len(), 99_999);
+    /// ```
+    pub fn append_exact<I>(&mut self, iter: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.len())?;
+
+        for value in iter {
+            // Only fails if iterator yields more values than its len.
+            self.push(value)?;
+        }
+        Ok(())
+    }
+
+    /// Resize the vec without copying data.
+    ///
+    /// # How it works ?
+    ///
+    /// 1. It first check we need to grow the segment.
+    /// 2. Call `Segment::<T>::open_rw` with a bigger capacity that what we already reserve.
+    ///    At this point, the file is mmap twice.
+    /// 3. Replace `self.segment` we newly mapped segment if there is no error.
+    /// 4. Update segment len to avoid calling drop on unwanted data.
+    ///
+    /// If a segment size limit is set, `MmapVecError::SegmentSizeExceeded` is returned
+    /// when `len + additional` elements do not fit in it.
+    /// Page rounding never goes above the limit: capacity is capped to the max number
+    /// of elements fitting in the limit instead.
+    ///
+    /// # Errors
+    ///
+    /// Current segment is only replaced once the new mapping succeeded, so on error
+    /// (`ENOSPC` from `ftruncate`, `ENOMEM` from `mmap`, ...) the vec is left intact:
+    /// `len`, `capacity` and content are unchanged and it can still be used.
+    /// Only the backing file may have been extended. Same goes for `reserve_exact`.
+    pub fn reserve(&mut self, additional: usize) -> Result<(), io::Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("reserve", additional, len = self.len()).entered();
+
+        let new_capacity = self.checked_capacity(additional)?;
+
+        if self.capacity() < new_capacity {
+            self.grow_to(self.rounded_capacity(new_capacity))?;
+        }
+
+        Ok(())
+    }
+
+    /// Same as `reserve` but grown segment is written to a new file, which then replaces
+    /// current one.
+    ///
+    /// # How it works ?
+    ///
+    /// 1. A bigger segment is created next to current file, with a `.grow` extension.
+    /// 2. Vec content is copied to it and flushed.
+    /// 3. New file is atomically renamed over current one, and vec now uses new segment.
+    ///
+    /// Until rename is done, current file is never modified, so a crash in the middle of
+    /// the grow cannot leave a truncated file. This is slower than `reserve` since data
+    /// is copied.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    /// v.reserve_via_copy(1000).unwrap();
+    /// assert!(v.capacity() >= 1003);
+    /// assert_eq!(&v[..], [1, 2, 3]);
+    /// ```
+    pub fn reserve_via_copy(&mut self, additional: usize) -> Result<(), io::Error> {
+        let new_capacity = self.checked_capacity(additional)?;
+        if self.capacity() >= new_capacity {
+            return Ok(());
+        }
+
+        let new_capacity = self.rounded_capacity(new_capacity);
+        let tmp_path = self.path.with_extension("grow");
+
+        let mut new_segment = match unsafe { self.segment.copy_to_file(&tmp_path, new_capacity) } {
+            Ok(new_segment) => new_segment,
+            Err(err) => {
+                let _ = Fs::remove_file(&tmp_path);
+                return Err(err);
+            }
+        };
+
+        if let Err(err) = Fs::rename(&tmp_path, &self.path) {
+            // Copied elements are still owned by current segment.
+            unsafe { new_segment.set_len(0) };
+            drop(new_segment);
+
+            let _ = Fs::remove_file(&tmp_path);
+            return Err(MmapVecError::io_with_path(&self.path, err));
+        }
+
+        self.replace_segment(new_segment);
+        self.grows += 1;
+        COUNT_VEC_GROW.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Apply `madvise` flags enabled in builder to current mapping.
+    pub(crate) fn advise_mapping(&self) {
+        if self.config.transparent_hugepages && self.segment.disk_size() >= HUGE_PAGE_SIZE {
+            self.segment.advice_hugepage();
+        }
+        if self.config.dont_fork {
+            self.segment.advice_dont_fork();
+        }
+        #[cfg(feature = "numa")]
+        if self.config.numa_interleave {
+            self.segment.advice_numa_interleave();
+        }
+    }
+
+    /// Round `capacity` to upper page, without going above segment size limit.
+    ///
+    /// When transparent huge pages are enabled, big segments are rounded to upper
+    /// huge page instead.
+    fn rounded_capacity(&self, capacity: usize) -> usize {
+        let element_size = mem::size_of::<T>();
+        let rounded_capacity = match capacity.checked_mul(element_size) {
+            Some(size) if self.config.transparent_hugepages && size >= HUGE_PAGE_SIZE => {
+                let huge_pages = size / HUGE_PAGE_SIZE + usize::from(size % HUGE_PAGE_SIZE != 0);
+                huge_pages
+                    .checked_mul(HUGE_PAGE_SIZE)
+                    .map_or(capacity, |size| size / element_size)
+            }
+            _ => round_capacity_to_page::<T>(capacity),
+        };
+        match self.config.segment_size_limit {
+            Some(limit) => rounded_capacity.min(limit / mem::size_of::<T>()),
+            None => rounded_capacity,
+        }
+    }
+
+    /// Same as `reserve` but return vec capacity after the call.
+    ///
+    /// Capacity is rounded up, so it is often bigger than `len + additional`: returned
+    /// value tells how many elements can be pushed before the next grow.
+    /// Comparing it with capacity before the call tells if the vec has grown.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    /// let per_page = page_size / 8;
+    ///
+    /// let mut v = MmapVec::<u64>::new();
+    /// assert_eq!(v.reserve_returning_capacity(100).unwrap(), per_page);
+    /// assert_eq!(v.reserve_returning_capacity(per_page - 1).unwrap(), per_page);
+    /// assert_eq!(v.reserve_returning_capacity(per_page + 1).unwrap(), 2 * per_page);
+    /// ```
+    #[inline(always)]
+    pub fn reserve_returning_capacity(&mut self, additional: usize) -> io::Result<usize> {
+        self.reserve(additional)?;
+        Ok(self.capacity())
+    }
+
+    /// Same as `reserve` but also load newly added pages in RAM.
+    ///
+    /// Page faults cost is paid up front rather than during next pushes,
+    /// which makes timing more predictable when growing a huge vec.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u64>::new();
+    /// v.reserve_and_prefault(100_000).unwrap();
+    /// assert!(v.capacity() >= 100_000);
+    /// ```
+    pub fn reserve_and_prefault(&mut self, additional: usize) -> Result<(), io::Error> {
+        let old_capacity = self.capacity();
+        self.reserve(additional)?;
+        self.segment.prefault_from(old_capacity)
+    }
+
+    /// Same as `reserve` but without rounding capacity to page size.
+    ///
+    /// Underlying file will be sized to exactly `len + additional` elements,
+    /// which helps to control disk usage when dealing with many vecs.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let mut v = MmapVec::<u64>::new();
+    /// v.reserve_exact(10).unwrap();
+    /// assert_eq!(v.capacity(), 10);
+    /// assert_eq!(v.disk_size(), 80);
+    /// ```
+    pub fn reserve_exact(&mut self, additional: usize) -> Result<(), io::Error> {
+        let new_capacity = self.checked_capacity(additional)?;
+
+        if self.capacity() < new_capacity {
+            self.grow_to(new_capacity)?;
+        }
+
+        Ok(())
+    }
+
+    /// Shrink the backing file as much as possible.
+    ///
+    /// Capacity is rounded to page size, like `reserve` does.
+    /// If vec is empty, backing file is removed and created again on next grow.
+    ///
+    /// # How it works ?
+    ///
+    /// Segment is mapped again with a smaller capacity, which truncates the file,
+    /// then the old mapping is released. If mapping fails, file size is restored.
+    pub fn shrink_to_fit(&mut self) -> io::Result<()> {
+        let new_capacity = round_capacity_to_page::<T>(self.len());
+        if new_capacity >= self.capacity() {
+            return Ok(());
+        }
+
+        if new_capacity == 0 {
+            self.replace_segment(Segment::null());
+            return Fs::remove_file(&self.path);
+        }
+
+        match self.open_segment(new_capacity) {
+            Ok(new_segment) => {
+                self.replace_segment(new_segment);
+                Ok(())
+            }
+            Err(err) => {
+                // Current mapping must remain backed by the file.
+                let _ = Fs::open(&self.path)
+                    .and_then(|file| unsafe { Fs::ftruncate(&file, self.disk_size()) });
+                Err(err)
+            }
+        }
+    }
+
+    /// Make room for one more element, growing the vec if it is full.
+    ///
+    /// Only the capacity check is inlined in `push`: growing is kept out of line.
+    #[inline(always)]
+    fn reserve_one(&mut self) -> io::Result<()> {
+        if !self.segment.is_full() {
+            return Ok(());
+        }
+        self.grow_one()
+    }
+
+    /// Grow full vec to make room for at least one more element.
+    #[inline(never)]
+    #[cold]
+    fn grow_one(&mut self) -> io::Result<()> {
+        // Grow by at least one element when `T` is bigger than a page.
+        let min_capacity = (page_size() / mem::size_of::<T>()).max(1);
+        let mut additional = std::cmp::max(self.len(), min_capacity);
+
+        // Do not grow above segment size limit, unless there is no more room at all.
+        if let Some(limit) = self.config.segment_size_limit {
+            let max_capacity = limit / mem::size_of::<T>();
+            additional = additional
+                .min(max_capacity.saturating_sub(self.len()))
+                .max(1);
+        }
+        self.reserve(additional)
+    }
+
+    /// Compute capacity required to hold `additional` more elements.
+    fn checked_capacity(&self, additional: usize) -> io::Result<usize> {
+        let capacity = self.len().checked_add(additional).ok_or_else(|| {
+            io::Error::from(MmapVecError::CapacityOverflow {
+                capacity: usize::MAX,
+                element_size: mem::size_of::<T>(),
+            })
+        })?;
+
+        check_segment_size_limit::<T>(capacity, self.config.segment_size_limit)?;
+        Ok(capacity)
+    }
+
+    /// Map vec path again with given capacity, adding a guard page if enabled.
+    ///
+    /// If segment folder is missing or is not a folder, a `MmapVecError::InvalidStoreDir`
+    /// naming it is returned instead of the raw error on segment file.
+    fn open_segment(&self, capacity: usize) -> io::Result<Segment<T>> {
+        let result = if self.config.check_free_space {
+            check_free_space::<T>(&self.path, capacity)
+        } else {
+            Ok(())
+        };
+
+        result
+            .and_then(|()| {
+                if self.config.guard_pages {
+                    Segment::open_rw_guarded(&self.path, capacity)
+                } else {
+                    Segment::open_rw(&self.path, capacity)
+                }
+            })
+            .map_err(|err| check_store_dir(&self.path, err))
+    }
+
+    /// Remap vec segment with a bigger capacity.
+    fn grow_to(&mut self, new_capacity: usize) -> Result<(), io::Error> {
+        debug_assert!(new_capacity > self.segment.capacity());
+
+        let old_capacity = self.segment.capacity();
+
+        let remapped = self.remap_grow(new_capacity)?;
+        if !remapped {
+            // Map again path with a new segment but with bigger capacity.
+            let new_segment = self.open_segment(new_capacity)?;
+            debug_assert!(new_segment.capacity() > self.segment.capacity());
+            self.replace_segment(new_segment);
+        }
+        self.grows += 1;
+        COUNT_VEC_GROW.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            path = %self.path.display(),
+            old_capacity,
+            new_capacity = self.segment.capacity(),
+            bytes = self.disk_size(),
+            remapped,
+            "mmap vec grown"
+        );
+
+        if let Some(byte) = self.config.poison_spare {
+            self.segment.poison_from(old_capacity, byte);
+        }
+        if self.config.warm_new_capacity {
+            // Best effort: data is already safe in new segment.
+            let _ = self.segment.prefault_from(old_capacity);
+        }
+        Ok(())
+    }
+
+    /// Try to grow current mapping in place using `mremap`.
+    ///
+    /// Return `false` if it cannot be used, so caller has to map file again: on other
+    /// platforms than Linux, before first mapping, with guard pages (they would be moved
+    /// along), with background flush (flush thread may be using current address),
+    /// or if `mremap` failed.
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    fn remap_grow(&mut self, new_capacity: usize) -> io::Result<bool> {
+        #[cfg(target_os = "linux")]
+        {
+            #[cfg(feature = "background-flush")]
+            let flushing = self.background_flush.is_some();
+            #[cfg(not(feature = "background-flush"))]
+            let flushing = false;
+
+            if !flushing && !self.config.guard_pages && !self.segment.addr.is_null() {
+                if self.config.check_free_space {
+                    check_free_space::<T>(&self.path, new_capacity)?;
+                }
+                if self.segment.remap_grow(&self.path, new_capacity)? {
+                    self.advise_mapping();
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Replace current segment with one mapping the same data.
+    fn replace_segment(&mut self, new_segment: Segment<T>) {
+        let current_len = self.len();
+
+        // At this point we cannot panic anymore !
+        // We have to carefully unmap region to avoid calling multiple times drop
+        let mut old_segment = mem::replace(&mut self.segment, new_segment);
+        debug_assert_ne!(old_segment.addr, self.segment.addr);
+
+        // Make sure flush thread does not use old segment anymore before it is unmapped.
+        #[cfg(feature = "background-flush")]
+        if let Some(background_flush) = &self.background_flush {
+            background_flush.update(&self.segment);
+        }
+
+        // Update capacity to nothing should be dropped twice.
+        unsafe {
+            old_segment.set_len(0);
+            self.segment.set_len(current_len);
+        }
+
+        self.advise_mapping();
+    }
+
+    /// Move backing file to `dir`, keeping its file name.
+    ///
+    /// # How it works ?
+    ///
+    /// 1. Flush current segment.
+    /// 2. Rename file to new location. Current mapping still points to the
+    ///    renamed file, so there is nothing else to do.
+    /// 3. If `dir` is on another device, segment is moved to a copy of the file
+    ///    with `Segment::remap_to`, and the old file is removed.
+    ///
+    /// Folder needs to exists. On error, vec still points to valid data.
+    pub fn move_to_dir<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        let file_name = self.path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "segment path has no file name")
+        })?;
+        let new_path = dir.as_ref().join(file_name);
+
+        // Segment file has not been created yet.
+        if self.capacity() == 0 {
+            self.path = new_path;
+            return Ok(());
+        }
+
+        self.flush()?;
+
+        match Fs::rename(&self.path, &new_path) {
+            Ok(()) => {
+                self.path = new_path;
+                return Ok(());
+            }
+            Err(err) if err.raw_os_error() == Some(libc::EXDEV) => {}
+            Err(err) => return Err(MmapVecError::io_with_path(new_path, err)),
+        }
+
+        // Cross device move.
+        // Make sure flush thread does not use current mapping while it is replaced.
+        #[cfg(feature = "background-flush")]
+        if let Some(background_flush) = &self.background_flush {
+            background_flush.update(&Segment::<T>::null());
+        }
+
+        let result = self.segment.remap_to(&new_path);
+
+        #[cfg(feature = "background-flush")]
+        if let Some(background_flush) = &self.background_flush {
+            background_flush.update(&self.segment);
+        }
+
+        match result {
+            Ok(()) => {
+                self.advise_mapping();
+                let old_path = mem::replace(&mut self.path, new_path);
+                let _ = Fs::remove_file(&old_path);
+                Ok(())
+            }
+            Err(err) => {
+                let _ = Fs::remove_file(&new_path);
+                Err(err)
+            }
+        }
+    }
+
+    /// Append `count` elements read from `reader` to the vec.
+    ///
+    /// Bytes are read straight into the mapped segment, without any intermediate buffer.
+    /// They are expected to be in native byte order.
+    ///
+    /// If reader does not contain enough bytes, an `io::ErrorKind::UnexpectedEof` error is
+    /// returned and vec len is not updated.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let bytes: Vec<u8> = [1u32, 2, 3].iter().flat_map(|x| x.to_ne_bytes()).collect();
+    ///
+    /// let mut v = MmapVec::<u32>::new();
+    /// v.fill_from_reader(bytes.as_slice(), 3).unwrap();
+    /// assert_eq!(&v[..], [1, 2, 3]);
+    /// ```
+    pub fn fill_from_reader<R: io::Read>(&mut self, mut reader: R, count: usize) -> io::Result<()>
+    where
+        T: Pod,
+    {
+        if count == 0 {
+            return Ok(());
+        }
+
+        self.reserve(count)?;
+
+        let len = self.len();
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(
+                self.segment.addr.add(len).cast::<u8>(),
+                count * mem::size_of::<T>(),
+            )
+        };
+        reader.read_exact(bytes)?;
+
+        unsafe { self.segment.set_len(len + count) };
+        Ok(())
+    }
+
+    /// Write raw bytes of the vec content to `writer`.
+    ///
+    /// Bytes are written in a single `write_all` call, straight from the mapped segment.
+    ///
+    /// Values are written in native byte order, so output is only portable between
+    /// machines sharing the same endianness. Use `fill_from_reader` to load them back.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u16>::try_from([1, 2, 3]).unwrap();
+    ///
+    /// let mut bytes = Vec::new();
+    /// v.write_to(&mut bytes).unwrap();
+    ///
+    /// let mut other = MmapVec::<u16>::new();
+    /// other.fill_from_reader(bytes.as_slice(), 3).unwrap();
+    /// assert_eq!(v, other);
+    /// ```
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        T: Pod,
+    {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                self.segment.addr.cast::<u8>(),
+                self.len() * mem::size_of::<T>(),
+            )
+        };
+        writer.write_all(bytes)
+    }
+
+    /// Check if vec content address is aligned for `U`.
+    ///
+    /// Segments are mapped at page boundaries, so this holds for every type with an
+    /// alignment up to page size. It is meant to be asserted before unsafe casts of
+    /// vec content, and is checked by `as_slice_of`.
+    ///
+    /// Empty vec without any mapping uses a dangling pointer aligned for `T` only.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    /// assert!(v.is_well_aligned_for::<u64>());
+    /// assert!(v.is_well_aligned_for::<u128>());
+    /// ```
+    #[inline(always)]
+    pub fn is_well_aligned_for<U>(&self) -> bool {
+        self.as_ptr() as usize % mem::align_of::<U>() == 0
+    }
+
+    /// View vec content as a slice of another plain old data type, without copy.
+    ///
+    /// `None` is returned if content address is not aligned for `U`, if content byte size
+    /// is not a multiple of `size_of::<U>()`, or if `U` is zero sized.
+    /// An empty vec always gives an empty slice.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u32>::try_from([1, 2, 3, 4]).unwrap();
+    ///
+    /// let bytes = v.as_slice_of::<u8>().unwrap();
+    /// assert_eq!(bytes.len(), 16);
+    /// assert_eq!(v.as_slice_of::<u64>().map(|x| x.len()), Some(2));
+    /// assert_eq!(v.as_slice_of::<[u8; 3]>(), None);
+    /// ```
+    pub fn as_slice_of<U: Pod>(&self) -> Option<&[U]>
+    where
+        T: Pod,
+    {
+        if mem::size_of::<U>() == 0 {
+            return None;
+        }
+        if self.is_empty() {
+            return Some(&[]);
+        }
+
+        let byte_len = self.len() * mem::size_of::<T>();
+        if !self.is_well_aligned_for::<U>() || byte_len % mem::size_of::<U>() != 0 {
+            return None;
+        }
+        let ptr = self.segment.addr.cast::<U>();
+
+        // Both types are `Pod`, so every bit pattern of `T` content is a valid `U`.
+        Some(unsafe { slice::from_raw_parts(ptr, byte_len / mem::size_of::<U>()) })
+    }
+
+    /// Inform the kernel how the complete vec segment will be access.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::{AccessPattern, MmapVec};
+    /// let v = MmapVec::<u64>::try_from([8, 5, 3]).unwrap();
+    /// v.advise(AccessPattern::Sequential);
+    /// assert_eq!(v.iter().sum::<u64>(), 16);
+    /// ```
+    ///
+    /// See `Segment::advise` for more details.
+    #[inline(always)]
+    pub fn advise(&self, pattern: AccessPattern) {
+        self.segment.advise(pattern)
+    }
+
+    /// Inform the kernel that the complete segment will be access in a near future.
+    #[inline(always)]
+    pub fn advice_prefetch_all_pages(&self) {
+        self.segment.advice_prefetch_all_pages()
+    }
+
+    /// Inform the kernel that underlying page for `index` will be access in a near future.
+    #[inline(always)]
+    pub fn advice_prefetch_page_at(&self, index: usize) {
+        self.segment.advice_prefetch_page_at(index)
+    }
+
+    /// Inform the kernel that underlying pages for elements in `range` will be access in a near future.
+    ///
+    /// See `Segment::advice_prefetch_range` for more details.
+    #[inline(always)]
+    pub fn advice_prefetch_range(&self, range: Range<usize>) {
+        self.segment.advice_prefetch_range(range)
+    }
+
+    /// Get sub slice of the vec after prefetching its pages.
+    ///
+    /// Range is clamped to vec len, so this function never panic.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u8>::try_from([1, 2, 3, 4]).unwrap();
+    /// assert_eq!(v.slice_prefetched(1..3), [2, 3]);
+    /// assert_eq!(v.slice_prefetched(2..100), [3, 4]);
+    /// assert_eq!(v.slice_prefetched(50..100), []);
+    /// ```
+    pub fn slice_prefetched(&self, range: Range<usize>) -> &[T] {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+
+        self.segment.advice_prefetch_range(start..end);
+        &self[start..end]
+    }
+
+    /// Inform the kernel that underlying pages for strided indexes will be access in a near future.
+    ///
+    /// See `Segment::advice_prefetch_strided` for more details.
+    #[inline(always)]
+    pub fn advice_prefetch_strided(&self, start: usize, stride: usize, count: usize) {
+        self.segment.advice_prefetch_strided(start, stride, count)
+    }
+
+    /// Iterate over `(index, &value)` pairs, prefetching pages ahead of current index.
+    ///
+    /// Pages are prefetched by blocks of `lookahead_pages` pages (at least 1), so next block
+    /// is loaded while current one is read.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u64>::try_from([8, 5, 3]).unwrap();
+    /// let items: Vec<_> = v.iter_indexed_prefetched(4).collect();
+    /// assert_eq!(items, [(0, &8), (1, &5), (2, &3)]);
+    /// ```
+    #[inline(always)]
+    pub fn iter_indexed_prefetched(&self, lookahead_pages: usize) -> IndexedPrefetchIter<'_, T> {
+        IndexedPrefetchIter::new(&self.segment, lookahead_pages)
+    }
+
+    /// Search for first element matching `pred` and return its index.
+    ///
+    /// Vec is scanned by blocks of pages, next block being prefetched while
+    /// current one is checked. This avoids stalling on every page when vec is not in RAM.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u64>::try_from([8, 5, 3, 12]).unwrap();
+    /// assert_eq!(v.find_prefetched(|x| *x < 5), Some(2));
+    /// assert_eq!(v.find_prefetched(|x| *x > 100), None);
+    /// ```
+    pub fn find_prefetched<F>(&self, mut pred: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.try_for_each_block_prefetched(|start, block| {
+            block
+                .iter()
+                .position(&mut pred)
+                .map(|offset| start + offset)
+        })
+    }
+
+    /// Sum of all elements, prefetching pages during the scan.
+    ///
+    /// Like `Iterator::sum`, this can overflow.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u64>::try_from([8, 5, 3, 12]).unwrap();
+    /// assert_eq!(v.sum(), 28);
+    /// assert_eq!(v.min(), Some(3));
+    /// assert_eq!(v.max(), Some(12));
+    /// assert_eq!(v.mean(), Some(7.0));
+    /// ```
+    pub fn sum(&self) -> T
+    where
+        T: Numeric,
+    {
+        let mut total = std::iter::empty::<T>().sum::<T>();
+        self.try_for_each_block_prefetched::<(), _>(|_, block| {
+            total = total + block.iter().copied().sum::<T>();
+            None
+        });
+        total
+    }
+
+    /// Smallest element, or `None` if vec is empty.
+    ///
+    /// Values that cannot be compared (like `NaN`) are skipped.
+    pub fn min(&self) -> Option<T>
+    where
+        T: Numeric,
+    {
+        self.fold_prefetched(|min, x| x < min)
+    }
+
+    /// Biggest element, or `None` if vec is empty.
+    ///
+    /// Values that cannot be compared (like `NaN`) are skipped.
+    pub fn max(&self) -> Option<T>
+    where
+        T: Numeric,
+    {
+        self.fold_prefetched(|max, x| x > max)
+    }
+
+    /// Mean of all elements as `f64`, or `None` if vec is empty.
+    pub fn mean(&self) -> Option<f64>
+    where
+        T: Numeric,
+    {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut total = 0.0;
+        self.try_for_each_block_prefetched::<(), _>(|_, block| {
+            total += block.iter().map(|x| x.to_f64()).sum::<f64>();
+            None
+        });
+        Some(total / self.len() as f64)
+    }
+
+    /// Project every element through `f` into a new vec.
+    ///
+    /// This is handy to extract a single column from row oriented data, so it can be
+    /// processed in a contiguous (SIMD friendly) way.
+    /// New vec is created once with exact capacity, using same segment builder and settings.
+    /// Source pages are prefetched ahead while scanning, like `sum` or `find_prefetched`.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<(u32, f64)>::try_from([(1, 0.5), (2, 1.5)]).unwrap();
+    /// let prices = v.map_to_vec(|row| row.1).unwrap();
+    /// assert_eq!(&prices[..], [0.5, 1.5]);
+    /// ```
+    pub fn map_to_vec<U, F>(&self, mut f: F) -> io::Result<MmapVec<U, B>>
+    where
+        B: Clone,
+        F: FnMut(&T) -> U,
+    {
+        let mut output = MmapVec::open_with_config(self.builder.clone(), self.len(), self.config)?;
+
+        self.try_for_each_block_prefetched::<(), _>(|_, block| {
+            for row in block {
+                if output.push_within_capacity(f(row)).is_err() {
+                    panic_bad_capacity();
+                }
+            }
+            None
+        });
+        Ok(output)
+    }
+
+    /// Keep first comparable element for which `replace(current, x)` holds.
+    fn fold_prefetched<F>(&self, mut replace: F) -> Option<T>
+    where
+        T: Numeric,
+        F: FnMut(T, T) -> bool,
+    {
+        let mut output: Option<T> = None;
+        self.try_for_each_block_prefetched::<(), _>(|_, block| {
+            for &x in block {
+                match output {
+                    // Skip values not equal to themself (`NaN`).
+                    _ if x.partial_cmp(&x).is_none() => {}
+                    Some(current) if !replace(current, x) => {}
+                    _ => output = Some(x),
+                }
+            }
+            None
+        });
+        output
+    }
+
+    /// Call `f` with `(start_index, block)` for every block of pages of the vec.
+    ///
+    /// Next block is prefetched while current one is processed.
+    /// Scan stops as soon as `f` returns some value.
+    fn try_for_each_block_prefetched<R, F>(&self, mut f: F) -> Option<R>
+    where
+        F: FnMut(usize, &[T]) -> Option<R>,
+    {
+        let len = self.len();
+        let block_len = (page_size() / mem::size_of::<T>()).max(1) * PREFETCH_AHEAD_PAGES;
+
+        self.segment.advice_prefetch_range(0..block_len);
+
+        let mut start = 0;
+        while start < len {
+            let end = start.saturating_add(block_len).min(len);
+            self.segment
+                .advice_prefetch_range(end..end.saturating_add(block_len));
+
+            if let Some(output) = f(start, &self[start..end]) {
+                return Some(output);
+            }
+            start = end;
+        }
+
+        None
+    }
+
+    /// Inform the kernel that pages covering elements in `range` are not needed for now.
+    ///
+    /// See `Segment::advice_free_range` for more details.
+    #[inline(always)]
+    pub fn advice_free_range(&self, range: Range<usize>) {
+        self.segment.advice_free_range(range)
+    }
+
+    /// Count pages of the vec currently resident in RAM.
+    ///
+    /// See `Segment::resident_pages` for more details.
+    #[inline(always)]
+    pub fn resident_pages(&self) -> io::Result<usize> {
+        self.segment.resident_pages()
+    }
+
+    /// Flush vec content to disk.
+    ///
+    /// This function blocks until all pages are written.
+    #[inline(always)]
+    pub fn flush(&self) -> io::Result<()> {
+        self.segment.flush()
+    }
+
+    /// Write a point-in-time copy of vec content to `dest`.
+    ///
+    /// Mapping is flushed first, then the first `len` elements of the backing file
+    /// are copied. Vec can still be used after this call.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u32>::try_from([1, 2, 3]).unwrap();
+    ///
+    /// let dest = std::env::temp_dir().join("mmap_vec_snapshot_example.seg");
+    /// v.snapshot_to(&dest).unwrap();
+    /// assert_eq!(std::fs::metadata(&dest).unwrap().len(), 12);
+    /// # std::fs::remove_file(&dest).unwrap();
+    /// ```
+    pub fn snapshot_to<P: AsRef<Path>>(&self, dest: P) -> io::Result<()> {
+        let dest = dest.as_ref();
+        let mut output = File::create(dest).map_err(|err| MmapVecError::io_with_path(dest, err))?;
+
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        self.flush()?;
+
+        let input =
+            File::open(&self.path).map_err(|err| MmapVecError::io_with_path(&self.path, err))?;
+        let len_bytes = (self.len() * mem::size_of::<T>()) as u64;
+        io::copy(&mut input.take(len_bytes), &mut output)
+            .map_err(|err| MmapVecError::io_with_path(dest, err))?;
+
+        Ok(())
+    }
+
+    /// Turn vec into a read only vec that can be cheaply cloned and shared between threads.
+    ///
+    /// Segment is protected as read only and no data is copied.
+    /// See `SharedMmapVec` for more details.
+    pub fn into_shared(mut self) -> io::Result<SharedMmapVec<T, B>>
+    where
+        T: Copy,
+    {
+        #[cfg(feature = "background-flush")]
+        self.disable_background_flush();
+
+        // Do not keep unused capacity mapped for nothing.
+        self.shrink_to_fit()?;
+        SharedMmapVec::new(self)
+    }
+
+    /// Decompose vec into its raw parts: `(addr, len, capacity, path)`.
+    ///
+    /// Nothing is unmapped nor removed: caller becomes responsible of dropping elements,
+    /// unmapping `capacity * size_of::<T>()` bytes from `addr` and removing file at `path`.
+    /// Easiest way to do so is to give parts back to `MmapVec::from_raw_parts`.
+    ///
+    /// Vec config (segment builder, growth options, ...) is lost.
+    pub fn into_raw_parts(self) -> (*mut T, usize, usize, PathBuf) {
+        let mut this = mem::ManuallyDrop::new(self);
+
+        // Stop flush thread, it is not allowed to outlive the vec.
+        #[cfg(feature = "background-flush")]
+        this.disable_background_flush();
+
+        // Each field is either moved out or dropped once, then `this` is never used again.
+        let (segment, path) = unsafe {
+            ptr::drop_in_place(&mut this.builder);
+            (ptr::read(&this.segment), ptr::read(&this.path))
+        };
+        let (addr, len, capacity) = segment.into_raw_parts();
+        (addr, len, capacity, path)
+    }
+
+    /// Save vec content to a self describing file at `path`.
+    ///
+    /// # File format
+    ///
+    /// File starts with a 32 bytes header, made of little endian fields:
+    /// - magic bytes `MMAPVEC\0`,
+    /// - format version (`u64`),
+    /// - element size in bytes (`u64`),
+    /// - vec len (`u64`).
+    ///
+    /// Raw bytes of the elements follow, in native byte order (see `write_to`).
+    ///
+    /// Unlike vec segment, this file is never removed by the crate.
+    /// Use `load` to read it back.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u32>::try_from([1, 2, 3]).unwrap();
+    ///
+    /// let path = std::env::temp_dir().join("mmap_vec_save_example.bin");
+    /// v.save(&path).unwrap();
+    ///
+    /// let other = MmapVec::<u32>::load(&path).unwrap();
+    /// assert_eq!(other, v);
+    /// assert!(MmapVec::<u64>::load(&path).is_err());
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()>
+    where
+        T: Pod,
+    {
+        let path = path.as_ref();
+        let with_path = |err| MmapVecError::io_with_path(path, err);
+
+        let mut header = [0; SAVE_HEADER_SIZE];
+        header[..8].copy_from_slice(&SAVE_MAGIC);
+        header[8..16].copy_from_slice(&SAVE_VERSION.to_le_bytes());
+        header[16..24].copy_from_slice(&(mem::size_of::<T>() as u64).to_le_bytes());
+        header[24..32].copy_from_slice(&(self.len() as u64).to_le_bytes());
+
+        let mut output = File::create(path).map_err(with_path)?;
+        output.write_all(&header).map_err(with_path)?;
+        self.write_to(&mut output).map_err(with_path)
+    }
+
+    /// Load a file written by `save` into a new vec.
+    ///
+    /// Header is validated first: `MmapVecError::InvalidHeader` is returned if file was
+    /// not written by `save` or is truncated, and `MmapVecError::ElementSizeMismatch` if it
+    /// contains elements of another size than `T`.
+    /// Types of same size cannot be told apart, so loading `u32` as `f32` succeeds.
+    ///
+    /// Content is copied to a new segment, so file at `path` is left untouched.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MmapVecError>
+    where
+        T: Pod,
+    {
+        let path = path.as_ref();
+        let file_io = |source| MmapVecError::FileIo {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let mut input = File::open(path).map_err(file_io)?;
+        let file_size = input.metadata().map_err(file_io)?.len();
+        if file_size < SAVE_HEADER_SIZE as u64 {
+            return Err(MmapVecError::InvalidHeader {
+                reason: "file is too small",
+            });
+        }
+
+        let mut header = [0; SAVE_HEADER_SIZE];
+        input.read_exact(&mut header).map_err(file_io)?;
+        let read_u64 = |offset: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&header[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+
+        if header[..8] != SAVE_MAGIC {
+            return Err(MmapVecError::InvalidHeader {
+                reason: "bad magic bytes",
+            });
+        }
+        if read_u64(8) != SAVE_VERSION {
+            return Err(MmapVecError::InvalidHeader {
+                reason: "unsupported version",
+            });
+        }
+
+        let element_size = mem::size_of::<T>();
+        if read_u64(16) != element_size as u64 {
+            return Err(MmapVecError::ElementSizeMismatch {
+                file_element_size: read_u64(16) as usize,
+                element_size,
+            });
+        }
+
+        let len = read_u64(24);
+        let data_size = len.checked_mul(element_size as u64);
+        if data_size != Some(file_size - SAVE_HEADER_SIZE as u64) {
+            return Err(MmapVecError::InvalidHeader {
+                reason: "file size does not match header len",
+            });
         }
+
+        let len = usize::try_from(len).map_err(|_| MmapVecError::CapacityOverflow {
+            capacity: usize::MAX,
+            element_size,
+        })?;
+        let mut vec = Self::with_capacity(len)?;
+        vec.fill_from_reader(input, len).map_err(file_io)?;
+        Ok(vec)
     }
 
-    /// Create a mmap vec with a given capacity.
+    /// Spawn a thread that periodically flush vec content to disk.
     ///
-    /// This function can fail if FS / IO failed.
-    #[inline(always)]
-    pub fn with_capacity(capacity: usize) -> io::Result<Self> {
-        MmapVecBuilder::new().capacity(capacity).try_build()
+    /// # Threading model
+    ///
+    /// The thread does not own any data. It only shares with the vec the
+    /// address and size of the current mapping behind a mutex.
+    /// When the vec grows, the mapping is updated before the old segment
+    /// is unmapped, so flush never happens on freed memory.
+    ///
+    /// A single thread is attached to a vec: calling this function again
+    /// replace previous one. Thread is stopped and joined when calling
+    /// `disable_background_flush` or when the vec is drop.
+    ///
+    /// Flush errors are ignored by the thread, call `flush` to get them.
+    ///
+    /// This function can fail if thread cannot be spawn.
+    #[cfg(feature = "background-flush")]
+    pub fn enable_background_flush(&mut self, interval: Duration) -> io::Result<()> {
+        self.disable_background_flush();
+        self.background_flush = Some(BackgroundFlush::spawn(&self.segment, interval)?);
+        Ok(())
     }
 
-    /// Currently used vec size.
+    /// Stop background flush thread if any.
+    #[cfg(feature = "background-flush")]
     #[inline(always)]
-    pub fn capacity(&self) -> usize {
-        self.segment.capacity()
+    pub fn disable_background_flush(&mut self) {
+        self.background_flush = None;
     }
 
-    /// Bytes use on disk for this vec.
-    #[inline(always)]
-    pub fn disk_size(&self) -> usize {
-        self.segment.disk_size()
+    /// Get underlying file path.
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
     }
 
-    /// Shortens the vec, keeping the first `new_len` elements and dropping
-    /// the rest.
+    /// Number of times this vec has been remapped to grow.
+    ///
+    /// Same as `MmapStats::vec_grows`, but only counting this vec. Vecs created from it
+    /// (`try_clone`, ...) start from 0.
     #[inline(always)]
-    pub fn truncate(&mut self, new_len: usize) {
-        self.segment.truncate(new_len);
+    pub fn grow_count(&self) -> u64 {
+        self.grows
     }
 
-    /// Remove `delete_count` element at beginning of the vec.
+    /// Keep backing file when vec is drop.
     ///
-    /// Element will be drop in place.
+    /// By default, file is removed with the vec. A persistent vec leaves it in place,
+    /// so its content can be mapped again later (e.g. with `Segment::open_rw` and
+    /// `MmapVec::from_segment`). File holds raw content of the whole capacity: vec len
+    /// and settings are not saved.
     ///
-    /// If delete count is greater than the segment len, then this call will be
-    /// equivalent to calling `clear` function.
+    /// This also applies to the owning iterator returned by `into_iter`: consuming a
+    /// persistent vec leaves its file, with values bitwise copied out but not erased.
     ///
     /// Example:
     /// ```rust
     /// # use mmap_vec::MmapVec;
-    /// let mut v = MmapVec::<u8>::new();
-    /// assert!(v.push(8).is_ok());
-    /// assert!(v.push(5).is_ok());
-    /// assert!(v.push(3).is_ok());
-    /// assert!(v.push(12).is_ok());
-    /// assert_eq!(&v[..], &[8, 5, 3, 12]);
-    ///
-    /// v.truncate_first(2);
-    /// assert_eq!(&v[..], [3, 12]);
+    /// let mut v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    /// v.set_persistent(true);
+    /// let path = v.path();
     ///
-    /// v.truncate_first(100);
-    /// assert_eq!(&v[..], []);
+    /// assert_eq!(v.into_iter().sum::<u64>(), 6);
+    /// assert!(path.exists());
+    /// # std::fs::remove_file(&path).unwrap();
     /// ```
     #[inline(always)]
-    pub fn truncate_first(&mut self, delete_count: usize) {
-        self.segment.truncate_first(delete_count);
+    pub fn set_persistent(&mut self, persistent: bool) {
+        self.persistent = persistent;
     }
 
-    /// Clears the vec, removing all values.
+    /// Returns `true` if backing file is kept when vec is drop.
     #[inline(always)]
-    pub fn clear(&mut self) {
-        self.segment.clear();
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
     }
+}
 
-    /// Remove last value of the vec.
+impl<T> MmapVec<T, DefaultSegmentBuilder> {
+    /// Wrap an existing segment into a managed vec using default segment builder.
     ///
-    /// Value will be return if data structure is not empty.
+    /// See `from_segment` for more details.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::{MmapVec, Segment};
+    /// let path = std::env::temp_dir().join("mmap_vec_adopt_example.seg");
+    /// let mut segment = Segment::<u32>::open_rw(&path, 10).unwrap();
+    /// assert!(segment.push_within_capacity(42).is_ok());
+    ///
+    /// let v = unsafe { MmapVec::adopt(segment, path.clone()) };
+    /// assert_eq!(&v[..], [42]);
+    ///
+    /// drop(v);
+    /// assert!(!path.exists());
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// Same as `from_segment`.
     #[inline(always)]
-    pub fn pop(&mut self) -> Option<T> {
-        self.segment.pop()
+    pub unsafe fn adopt(segment: Segment<T>, path: PathBuf) -> Self {
+        Self::from_segment(segment, DefaultSegmentBuilder::default(), path)
     }
 
-    /// Append a value to the vec.
+    /// Build a vec from a mapping created elsewhere, typically by foreign code.
     ///
-    /// If vec is too small:
-    /// - new segment may be created.
-    /// - current segment may be resize.
+    /// Vec takes ownership of the mapping and of its file, like with `adopt`.
     ///
-    /// This is why this function can fail, because it depends on FS / IO calls.
-    pub fn push(&mut self, value: T) -> Result<(), io::Error> {
-        // Reserve some space if vec is full.
-        if self.capacity() == self.len() {
-            let min_capacity = page_size() / mem::size_of::<T>();
-            self.reserve(std::cmp::max(self.len(), min_capacity))?;
-        }
-
-        // Add new value to vec.
-        assert!(
-            self.push_within_capacity(value).is_ok(),
-            "Fail to push to newly created segment"
-        );
-
-        Ok(())
-    }
-
-    /// Try to push a new value to the data structure.
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u32>::try_from([1, 2, 3]).unwrap();
+    /// let (addr, len, capacity, path) = v.into_raw_parts();
     ///
-    /// If vec is too small, value will be return as an `Err`.
+    /// let v = unsafe { MmapVec::from_raw_parts(addr, len, capacity, path) };
+    /// assert_eq!(&v[..], [1, 2, 3]);
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// - `addr`, `len` and `capacity` must follow `Segment::from_raw_parts` contract:
+    ///   `addr` comes from a shared read / write `mmap` of `capacity` elements (not bytes),
+    /// - mapping must be backed by file at `path`, holding exactly `capacity` elements,
+    /// - `path` must not be owned by anything else: it is removed when vec is drop.
     #[inline(always)]
-    pub fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
-        self.segment.push_within_capacity(value)
+    pub unsafe fn from_raw_parts(addr: *mut T, len: usize, capacity: usize, path: PathBuf) -> Self {
+        Self::adopt(Segment::from_raw_parts(addr, len, capacity), path)
     }
 
-    /// Resize the vec without copying data.
+    /// Try cloning the vector into a new segment created under `dir`.
     ///
-    /// # How it works ?
+    /// Output vec uses a `DefaultSegmentBuilder` pointing to `dir`, so it keeps growing
+    /// there. Folder needs to exists.
+    /// Capacity of the new vec will be the same as source vec, like `try_clone`.
     ///
-    /// 1. It first check we need to grow the segment.
-    /// 2. Call `Segment::<T>::open_rw` with a bigger capacity that what we already reserve.
-    ///    At this point, the file is mmap twice.
-    /// 3. Replace `self.segment` we newly mapped segment if there is no error.
-    /// 4. Update segment len to avoid calling drop on unwanted data.
-    pub fn reserve(&mut self, additional: usize) -> Result<(), io::Error> {
-        let current_len = self.len();
-        let mut new_capacity = current_len + additional;
-
-        if self.capacity() < new_capacity {
-            // Round to upper page new capacity
-            let page_size = page_size();
-            let page_capacity = page_size / mem::size_of::<T>();
-            if new_capacity % page_capacity != 0 {
-                new_capacity += page_capacity - (new_capacity % page_capacity);
-            }
-            assert!(new_capacity > self.segment.capacity());
-
-            // Map again path with a new segment but with bigger capacity.
-            let new_segment = Segment::<T>::open_rw(&self.path, new_capacity)?;
-            debug_assert!(new_segment.capacity() > self.segment.capacity());
-
-            // At this point we cannot panic anymore !
-            // We have to carefully unmap region to avoid calling multiple times drop
-            let mut old_segment = mem::replace(&mut self.segment, new_segment);
-            assert_ne!(old_segment.addr, self.segment.addr);
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let dir = std::env::temp_dir().join("mmap_vec_clone_into_dir_example");
+    /// std::fs::create_dir_all(&dir).unwrap();
+    ///
+    /// let v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    /// let other = v.try_clone_into_dir(&dir).unwrap();
+    ///
+    /// assert_eq!(other, v);
+    /// assert!(other.path().starts_with(&dir));
+    /// ```
+    pub fn try_clone_into_dir<P: AsRef<Path>>(&self, dir: P) -> io::Result<Self>
+    where
+        T: Clone,
+    {
+        let builder = DefaultSegmentBuilder::with_path(dir);
+        let mut other = Self::open_with_config(builder, self.clone_capacity(), self.config)?;
 
-            // Update capacity to nothing should be dropped twice.
-            unsafe {
-                old_segment.set_len(0);
-                self.segment.set_len(current_len);
+        for row in &self[..] {
+            if other.push_within_capacity(row.clone()).is_err() {
+                panic_bad_capacity();
             }
         }
 
-        Ok(())
+        Ok(other)
     }
+}
 
-    /// Inform the kernel that the complete segment will be access in a near future.
-    #[inline(always)]
-    pub fn advice_prefetch_all_pages(&self) {
-        self.segment.advice_prefetch_all_pages()
-    }
+impl<T, B> MmapVec<T, B>
+where
+    B: SegmentBuilder + Clone,
+{
+    /// Move vec elements into new vecs of `chunk_len` elements each, one file per vec.
+    ///
+    /// Last vec may contain less elements. Every new vec capacity is exactly its len,
+    /// and source file is removed.
+    ///
+    /// All new segments are created before moving any element. On error (e.g. disk is
+    /// full), no element is moved and source vec is given back with the error, so its
+    /// content is never lost.
+    ///
+    /// Will panic if `chunk_len` is 0.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u8>::try_from([1, 2, 3, 4, 5]).unwrap();
+    /// let chunks = v.into_chunk_files(2).map_err(|(_, err)| err).unwrap();
+    ///
+    /// assert_eq!(chunks.len(), 3);
+    /// assert_eq!(&chunks[0][..], [1, 2]);
+    /// assert_eq!(&chunks[1][..], [3, 4]);
+    /// assert_eq!(&chunks[2][..], [5]);
+    /// ```
+    // Source vec is given back on error, this is the point of the big `Err` variant.
+    #[allow(clippy::result_large_err)]
+    pub fn into_chunk_files(mut self, chunk_len: usize) -> Result<Vec<Self>, (Self, io::Error)> {
+        assert!(chunk_len > 0, "Chunk len cannot be 0");
+
+        let len = self.len();
+        let mut chunks = Vec::with_capacity(len / chunk_len + 1);
+
+        for start in (0..len).step_by(chunk_len) {
+            let capacity = chunk_len.min(len - start);
+            match Self::open_with_config(self.builder.clone(), capacity, self.config) {
+                Ok(chunk) => chunks.push(chunk),
+                // Chunks created so far are still empty, they can be drop.
+                Err(err) => return Err((self, err)),
+            }
+        }
 
-    /// Inform the kernel that underlying page for `index` will be access in a near future.
-    #[inline(always)]
-    pub fn advice_prefetch_page_at(&self, index: usize) {
-        self.segment.advice_prefetch_page_at(index)
-    }
+        // Elements are moved bitwise, so they must not be drop by source vec.
+        unsafe {
+            for (index, chunk) in chunks.iter_mut().enumerate() {
+                let count = chunk.capacity();
+                ptr::copy_nonoverlapping(
+                    self.segment.addr.add(index * chunk_len),
+                    chunk.segment.addr,
+                    count,
+                );
+                chunk.segment.set_len(count);
+            }
+            self.segment.set_len(0);
+        }
 
-    /// Get underlying file path.
-    pub fn path(&self) -> PathBuf {
-        self.path.clone()
+        Ok(chunks)
     }
 }
 
@@ -336,29 +2225,113 @@ where
     /// Try cloning the vector.
     ///
     /// A new segment will be created for output vec.
-    /// Capacity of the new vec will be the same as source vec.
+    /// Capacity of the new vec will be the same as source vec, or 0 if source vec is empty.
     pub fn try_clone(&self) -> io::Result<Self> {
-        if self.len() == 0 {
-            return Ok(Self::default());
-        }
-
-        let other_path = self.builder.new_segment_path();
-        let mut other_segment = Segment::open_rw(&other_path, self.capacity())?;
+        let mut other =
+            Self::open_with_config(self.builder.clone(), self.clone_capacity(), self.config)?;
 
         // Bellow code could be optimize, but we have to deal with Clone implementation that can panic ...
         for row in &self[..] {
             // It is "safe" here to call panic on error since we already have reserved correct segment capacity.
             assert!(
-                other_segment.push_within_capacity(row.clone()).is_ok(),
+                other.push_within_capacity(row.clone()).is_ok(),
                 "Fail to push to newly cloned segment"
             );
         }
 
-        Ok(Self {
-            builder: self.builder.clone(),
-            segment: other_segment,
-            path: other_path,
-        })
+        Ok(other)
+    }
+
+    /// Create a new vec containing sorted and deduplicated elements of this vec.
+    ///
+    /// Source vec is left untouched. Like `try_clone`, a new backing file is created.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u8>::try_from([3, 1, 2, 3, 1]).unwrap();
+    /// let sorted = v.sorted_unique().unwrap();
+    ///
+    /// assert_eq!(&sorted[..], [1, 2, 3]);
+    /// assert_eq!(&v[..], [3, 1, 2, 3, 1]);
+    /// ```
+    pub fn sorted_unique(&self) -> io::Result<Self>
+    where
+        T: Ord,
+    {
+        let mut output = self.try_clone()?;
+        output.sort_unstable();
+        output.dedup_by(|a, b| a == b);
+        Ok(output)
+    }
+}
+
+impl<T, B> MmapVec<T, B>
+where
+    B: SegmentBuilder,
+    T: Clone,
+{
+    /// Clone the vec content into `target`, reusing its segment.
+    ///
+    /// Existing elements are overwritten in place, `target` is then truncated or
+    /// grown as needed. No new segment is created if `target` capacity is large enough.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let src = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    /// let mut dst = MmapVec::<u8>::try_from([4, 5, 6, 7, 8]).unwrap();
+    ///
+    /// src.clone_into(&mut dst).unwrap();
+    /// assert_eq!(dst, src);
+    /// ```
+    pub fn clone_into<B2: SegmentBuilder>(&self, target: &mut MmapVec<T, B2>) -> io::Result<()> {
+        target.truncate(self.len());
+
+        let (init, tail) = self.split_at(target.len());
+        target.clone_from_slice(init);
+
+        target.reserve(tail.len())?;
+        for value in tail {
+            if target.push_within_capacity(value.clone()).is_err() {
+                panic_bad_capacity();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copy elements in `range` to a new `Vec`.
+    ///
+    /// Range is clamped to vec len, so this function never panic.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVec;
+    /// let v = MmapVec::<u8>::try_from([1, 2, 3, 4]).unwrap();
+    /// assert_eq!(v.range_to_vec(1..3), [2, 3]);
+    /// assert_eq!(v.range_to_vec(2..), [3, 4]);
+    /// assert_eq!(v.range_to_vec(..=100), [1, 2, 3, 4]);
+    /// assert_eq!(v.range_to_vec(50..100), []);
+    /// ```
+    pub fn range_to_vec<R: RangeBounds<usize>>(&self, range: R) -> Vec<T> {
+        let len = self.len();
+
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        }
+        .min(len);
+
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        }
+        .min(end);
+
+        self[start..end].to_vec()
     }
 }
 
@@ -400,14 +2373,24 @@ where
     B2: SegmentBuilder,
     T: PartialEq<U>,
 {
-    #[inline(always)]
     fn eq(&self, other: &MmapVec<U, B2>) -> bool {
+        // Check len first, so pages of unequal vecs are never loaded.
+        if self.len() != other.len() {
+            return false;
+        }
+
+        // Vecs spanning many pages may not be in RAM.
+        if self.len() * mem::size_of::<T>() > page_size() {
+            self.segment.advice_prefetch_best_effort();
+            other.segment.advice_prefetch_best_effort();
+        }
+
         self[..] == other[..]
     }
 
     #[inline(always)]
     fn ne(&self, other: &MmapVec<U, B2>) -> bool {
-        self[..] != other[..]
+        !self.eq(other)
     }
 }
 
@@ -423,7 +2406,149 @@ where
     B: SegmentBuilder,
 {
     fn drop(&mut self) {
-        let _ = fs::remove_file(&self.path);
+        // Stop flush thread before segment is unmapped.
+        #[cfg(feature = "background-flush")]
+        self.disable_background_flush();
+
+        if self.persistent {
+            return;
+        }
+
+        let _result = Fs::remove_file(&self.path);
+
+        // Vec that never allocated has no file: this is not worth an event.
+        #[cfg(feature = "tracing")]
+        match &_result {
+            Ok(()) => tracing::debug!(path = %self.path.display(), "mmap vec file removed"),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => tracing::warn!(
+                path = %self.path.display(),
+                error = %err,
+                "fail to remove mmap vec file"
+            ),
+        }
+    }
+}
+
+impl<T, B> IntoIterator for MmapVec<T, B>
+where
+    B: SegmentBuilder,
+{
+    type Item = T;
+    type IntoIter = IntoIter<T, B>;
+
+    /// Creates a consuming iterator, moving each value out of the vec.
+    ///
+    /// Backing file is removed once iterator is drop, unless vec is persistent.
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self)
+    }
+}
+
+/// Concatenate all `parts` into a single new vec.
+///
+/// Total length is computed first, so a single segment is created with exact capacity
+/// and no grow happens while copying. Output vec uses segment builder and settings
+/// (size limit, guard pages, ...) of first part.
+///
+/// Example:
+/// ```rust
+/// # use mmap_vec::MmapVec;
+/// let parts = [
+///     MmapVec::<u8>::try_from([1, 2]).unwrap(),
+///     MmapVec::new(),
+///     MmapVec::try_from([3]).unwrap(),
+/// ];
+///
+/// let v = mmap_vec::concat(&parts).unwrap();
+/// assert_eq!(&v[..], [1, 2, 3]);
+/// assert_eq!(v.capacity(), 3);
+/// ```
+pub fn concat<T, B>(parts: &[MmapVec<T, B>]) -> io::Result<MmapVec<T, B>>
+where
+    T: Clone,
+    B: SegmentBuilder + Clone,
+{
+    let (builder, config) = match parts.first() {
+        Some(part) => (part.builder.clone(), part.config),
+        None => (B::default(), VecConfig::default()),
+    };
+    let total_len = parts
+        .iter()
+        .fold(0usize, |total, part| total.saturating_add(part.len()));
+
+    let mut output = MmapVec::open_with_config(builder, total_len, config)?;
+
+    for row in parts.iter().flat_map(|part| part.iter()) {
+        if output.push_within_capacity(row.clone()).is_err() {
+            panic_bad_capacity();
+        }
+    }
+    Ok(output)
+}
+
+/// Check that file system holding `path` has enough free space to grow it to
+/// `capacity` elements.
+pub(crate) fn check_free_space<T>(path: &Path, capacity: usize) -> io::Result<()> {
+    let current_size = fs::metadata(path).map_or(0, |metadata| metadata.len());
+    let required =
+        (capacity.saturating_mul(mem::size_of::<T>()) as u64).saturating_sub(current_size);
+    if required == 0 {
+        return Ok(());
+    }
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let available =
+        utils::available_space(dir).map_err(|err| MmapVecError::io_with_path(dir, err))?;
+
+    if required > available {
+        return Err(MmapVecError::InsufficientSpace {
+            required,
+            available,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Turn `err`, from creating segment at `path`, into a `MmapVecError::InvalidStoreDir`
+/// if segment folder is not usable. Otherwise `err` is returned as is.
+#[cold]
+pub(crate) fn check_store_dir(path: &Path, err: io::Error) -> io::Error {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => return err,
+    };
+
+    match fs::metadata(dir) {
+        Ok(metadata) if metadata.is_dir() => err,
+        _ => io::Error::new(
+            err.kind(),
+            MmapVecError::InvalidStoreDir {
+                path: dir.to_path_buf(),
+                source: err,
+            },
+        ),
+    }
+}
+
+/// Check that a segment of `capacity` elements fits in `limit` bytes.
+pub(crate) fn check_segment_size_limit<T>(
+    capacity: usize,
+    limit: Option<usize>,
+) -> Result<(), MmapVecError> {
+    match limit {
+        Some(limit) if capacity.saturating_mul(mem::size_of::<T>()) > limit => {
+            Err(MmapVecError::SegmentSizeExceeded {
+                size: capacity.saturating_mul(mem::size_of::<T>()),
+                limit,
+            })
+        }
+        _ => Ok(()),
     }
 }
 
@@ -474,17 +2599,36 @@ where
 {
     type Error = io::Error;
 
-    fn try_from(values: Vec<T>) -> Result<Self, Self::Error> {
-        let mut out = Self::with_capacity(values.len())?;
-        for val in values {
-            if out.push_within_capacity(val).is_err() {
-                panic_bad_capacity();
-            }
+    fn try_from(mut values: Vec<T>) -> Result<Self, Self::Error> {
+        let len = values.len();
+        let mut out = Self::with_capacity(len)?;
+        if len == 0 {
+            return Ok(out);
+        }
+
+        // Values are moved to the segment in a single copy, so source vec must
+        // forget them before its buffer is released.
+        unsafe {
+            values.set_len(0);
+            ptr::copy_nonoverlapping(values.as_ptr(), out.segment.addr, len);
+            out.segment.set_len(len);
         }
         Ok(out)
     }
 }
 
+impl<B> TryFrom<String> for MmapVec<u8, B>
+where
+    B: SegmentBuilder,
+{
+    type Error = io::Error;
+
+    #[inline(always)]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.into_bytes())
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<T, B> Serialize for MmapVec<T, B>
 where
@@ -535,8 +2679,17 @@ where
     {
         use serde::de::Error;
 
-        let capacity = seq.size_hint().unwrap_or(0);
-        let mut output = MmapVec::<T, B>::with_capacity(capacity).map_err(Error::custom)?;
+        // Cap size hint like serde `size_hint::cautious` does, so a corrupted length
+        // prefix does not create a huge segment before reading any element.
+        const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+        let max_capacity = MAX_PREALLOC_BYTES / mem::size_of::<T>().max(1);
+
+        let mut output = match seq.size_hint() {
+            Some(capacity) => MmapVec::<T, B>::with_capacity(capacity.min(max_capacity)),
+            // No hint, start with a full page instead of an empty segment.
+            None => MmapVecBuilder::<T, B>::new().try_build(),
+        }
+        .map_err(Error::custom)?;
 
         while let Some(element) = seq.next_element()? {
             output.push(element).map_err(Error::custom)?;
@@ -0,0 +1,20 @@
+use std::{iter::Sum, ops::Add};
+
+/// Primitive numeric types supported by `MmapVec` analytics helpers (`sum`, `mean`, ...).
+pub trait Numeric: Copy + PartialOrd + Add<Output = Self> + Sum + 'static {
+    /// Convert value to `f64`, possibly losing precision.
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_numeric {
+    ($($t:ty),*) => {
+        $(impl Numeric for $t {
+            #[inline(always)]
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        })*
+    };
+}
+
+impl_numeric!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
@@ -0,0 +1,24 @@
+/// Marker trait for "plain old data" types.
+///
+/// Such types can be safely read from / written to raw bytes.
+///
+/// # Safety
+///
+/// Implementing this trait means that:
+/// - Every bit pattern is a valid value of the type.
+/// - The type does not contain any padding bytes.
+/// - The type does not contain any pointer / reference.
+///
+/// This is typically the case of `#[repr(C)]` struct only containing numeric fields
+/// with no hole in their layout.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
@@ -0,0 +1,55 @@
+use std::mem;
+
+use crate::{utils::page_size, Segment};
+
+/// Iterator over `(index, &T)` that prefetches pages ahead of current position.
+///
+/// Created by `MmapVec::iter_indexed_prefetched`.
+#[derive(Debug)]
+pub struct IndexedPrefetchIter<'a, T> {
+    segment: &'a Segment<T>,
+    index: usize,
+    block_len: usize,
+    prefetched_end: usize,
+}
+
+impl<'a, T> IndexedPrefetchIter<'a, T> {
+    pub(crate) fn new(segment: &'a Segment<T>, lookahead_pages: usize) -> Self {
+        let page_capacity = (page_size() / mem::size_of::<T>()).max(1);
+        Self {
+            segment,
+            index: 0,
+            block_len: page_capacity.saturating_mul(lookahead_pages.max(1)),
+            prefetched_end: 0,
+        }
+    }
+}
+
+impl<'a, T> Iterator for IndexedPrefetchIter<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let segment: &'a Segment<T> = self.segment;
+        let value = segment.get(self.index)?;
+
+        // Keep at least one block prefetched ahead of current index.
+        while self.prefetched_end < segment.len()
+            && self.index.saturating_add(self.block_len) >= self.prefetched_end
+        {
+            let end = self.prefetched_end.saturating_add(self.block_len);
+            segment.advice_prefetch_range(self.prefetched_end..end);
+            self.prefetched_end = end;
+        }
+
+        let index = self.index;
+        self.index += 1;
+        Some((index, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.segment.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IndexedPrefetchIter<'a, T> {}
@@ -1,16 +1,21 @@
 use std::{
-    fs::{File, OpenOptions},
+    fmt,
+    fs::{self, File},
     io, mem,
-    ops::{Deref, DerefMut},
-    os::fd::AsRawFd,
+    ops::{Deref, DerefMut, Range},
     path::Path,
-    ptr, slice,
+    ptr::{self, NonNull},
+    slice,
     sync::atomic::Ordering,
 };
 
 use crate::{
+    advice::AccessPattern,
+    error::MmapVecError,
+    fs_ops::{Fs, FsOps},
     stats::{COUNT_ACTIVE_SEGMENT, COUNT_FTRUNCATE_FAILED, COUNT_MMAP_FAILED, COUNT_MUNMAP_FAILED},
-    utils::{check_zst, page_size},
+    utils::{check_zst, page_size, retry_on_eintr},
+    PREFETCH_AHEAD_PAGES,
 };
 
 /// Segment is a constant slice of type T that is memory mapped to disk.
@@ -18,11 +23,12 @@ use crate::{
 /// It is the basic building block of memory mapped data structure.
 ///
 /// It cannot growth / shrink.
-#[derive(Debug)]
 pub struct Segment<T> {
     pub(crate) addr: *mut T,
     len: usize,
     capacity: usize,
+    /// Size of `PROT_NONE` pages mapped after segment data.
+    guard_size: usize,
 }
 
 impl<T> Segment<T> {
@@ -34,42 +40,147 @@ impl<T> Segment<T> {
             addr: std::ptr::null_mut(),
             len: 0,
             capacity: 0,
+            guard_size: 0,
         }
     }
 
     /// Memory map a segment to disk.
     ///
     /// File will be created and init with computed capacity.
+    ///
+    /// Returned errors wrap a `MmapVecError::FileIo` recording `path`.
+    #[inline(always)]
     pub fn open_rw<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Self> {
+        Self::open_rw_with_guard(path, capacity, 0)
+    }
+
+    /// Same as `open_rw`, but also map a `PROT_NONE` guard page just after segment data.
+    ///
+    /// Any access past the last page of the segment faults immediately, instead of
+    /// touching an adjacent mapping. It costs one page of address space, and no disk
+    /// or RAM.
+    #[inline(always)]
+    pub(crate) fn open_rw_guarded<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Self> {
+        Self::open_rw_with_guard(path, capacity, page_size())
+    }
+
+    fn open_rw_with_guard<P: AsRef<Path>>(
+        path: P,
+        capacity: usize,
+        guard_size: usize,
+    ) -> io::Result<Self> {
         check_zst::<T>();
         if capacity == 0 {
             return Ok(Self::null());
         }
 
-        let file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(&path)?;
+        // Check capacity before creating any file.
+        segment_size::<T>(capacity)?;
+
+        let with_path = |err| MmapVecError::io_with_path(path.as_ref(), err);
+
+        let file = Fs::open(path.as_ref()).map_err(with_path)?;
 
         // Fill the file with 0
-        unsafe { ftruncate::<T>(&file, capacity) }?;
+        unsafe { ftruncate::<T>(&file, capacity) }.map_err(with_path)?;
 
         // Map the block
-        let addr = unsafe { mmap(&file, capacity) }?;
+        let addr = unsafe { mmap(&file, capacity, guard_size) }.map_err(with_path)?;
         Ok(Self {
             addr,
             len: 0,
             capacity,
+            guard_size,
         })
     }
 
+    /// Build a segment from a mapping created elsewhere (e.g. by foreign code).
+    ///
+    /// Segment takes ownership of the mapping: it will be unmapped when segment is drop.
+    ///
+    /// # Safety
+    ///
+    /// - `addr` must be null with `capacity` 0 and `len` 0, or be the start of a
+    ///   shared read / write mapping of exactly `capacity * size_of::<T>()` bytes,
+    ///   as returned by `mmap`, not owned by anything else,
+    /// - `capacity` is a number of elements, not bytes,
+    /// - `addr` must be properly aligned for `T`,
+    /// - `len` must be `<= capacity`, and first `len` elements must be initialized.
+    pub unsafe fn from_raw_parts(addr: *mut T, len: usize, capacity: usize) -> Self {
+        check_zst::<T>();
+        debug_assert!(len <= capacity);
+        debug_assert_eq!(addr.is_null(), capacity == 0);
+
+        if !addr.is_null() {
+            COUNT_ACTIVE_SEGMENT.fetch_add(1, Ordering::Relaxed);
+        }
+        Self {
+            addr,
+            len,
+            capacity,
+            guard_size: 0,
+        }
+    }
+
+    /// Decompose segment into its raw parts: `(addr, len, capacity)`.
+    ///
+    /// Mapping is left untouched, except guard pages which are unmapped: caller becomes
+    /// responsible of dropping elements and of unmapping `capacity * size_of::<T>()` bytes
+    /// from `addr`. Parts can be given back to `from_raw_parts`.
+    pub fn into_raw_parts(self) -> (*mut T, usize, usize) {
+        let this = mem::ManuallyDrop::new(self);
+
+        if !this.addr.is_null() {
+            if this.guard_size > 0 {
+                let size = mapping_size(this.disk_size(), this.guard_size);
+                unsafe {
+                    let guard_addr = this.addr.cast::<u8>().add(size - this.guard_size);
+                    let _ = retry_on_eintr(|| Fs::munmap(guard_addr.cast(), this.guard_size));
+                }
+            }
+            COUNT_ACTIVE_SEGMENT.fetch_sub(1, Ordering::Relaxed);
+        }
+        (this.addr, this.len, this.capacity)
+    }
+
+    /// Check that file at `path` can hold a whole number of `T`.
+    ///
+    /// This is a lightweight check that does not map the file.
+    /// Returns number of elements the file can hold.
+    pub fn validate_file<P: AsRef<Path>>(path: P) -> Result<usize, MmapVecError> {
+        check_zst::<T>();
+        let file_size = fs::metadata(&path)
+            .map_err(|source| MmapVecError::FileIo {
+                path: path.as_ref().to_path_buf(),
+                source,
+            })?
+            .len();
+        let element_size = mem::size_of::<T>();
+
+        if file_size % element_size as u64 != 0 {
+            return Err(MmapVecError::InvalidFileSize {
+                file_size,
+                element_size,
+            });
+        }
+
+        Ok((file_size / element_size as u64) as usize)
+    }
+
     /// Currently used segment size.
     #[inline(always)]
     pub fn capacity(&self) -> usize {
         self.capacity
     }
 
+    /// Returns `true` if there is no more room for a new element.
+    ///
+    /// Reads fields directly, without building a slice like `len` does through `Deref`.
+    #[inline(always)]
+    pub(crate) fn is_full(&self) -> bool {
+        self.len == self.capacity
+    }
+
     /// Shortens the segment, keeping the first `new_len` elements and dropping
     /// the rest.
     pub fn truncate(&mut self, new_len: usize) {
@@ -91,8 +202,11 @@ impl<T> Segment<T> {
     ///
     /// If delete count is greater than the segment len, then this call will be
     /// equivalent to calling `clear` function.
-    pub fn truncate_first(&mut self, delete_count: usize) {
-        let new_len = self.len.saturating_add_signed(-(delete_count as isize));
+    ///
+    /// Number of elements actually removed will be return.
+    pub fn truncate_first(&mut self, delete_count: usize) -> usize {
+        let delete_count = delete_count.min(self.len);
+        let new_len = self.len - delete_count;
         if new_len == 0 {
             self.clear()
         } else {
@@ -103,19 +217,107 @@ impl<T> Segment<T> {
                 self.set_len(new_len);
             }
         }
+        delete_count
+    }
+
+    /// Removes all but the first of consecutive elements in the segment satisfying
+    /// a given equality relation.
+    ///
+    /// Same semantic as `Vec::dedup_by`: `same_bucket(a, b)` is called with `a`
+    /// being the element after `b`, and `a` is removed if it returns `true`.
+    ///
+    /// Removed elements are drop in place and remaining ones are compacted
+    /// at beginning of the segment.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let len = self.len;
+        if len <= 1 {
+            return;
+        }
+
+        // If `same_bucket` or drop panic, remaining elements will be leaked
+        // instead of being drop twice.
+        unsafe { self.set_len(0) };
+
+        let block_len = prefetch_block_len::<T>();
+        let mut write = 1;
+        for read in 1..len {
+            unsafe {
+                if (read - 1) % block_len == 0 {
+                    prefetch_elements(self.addr, read, read.saturating_add(2 * block_len).min(len));
+                }
+                let read_ptr = self.addr.add(read);
+                let prev_ptr = self.addr.add(write - 1);
+
+                if same_bucket(&mut *read_ptr, &mut *prev_ptr) {
+                    ptr::drop_in_place(read_ptr);
+                } else {
+                    ptr::copy(read_ptr, self.addr.add(write), 1);
+                    write += 1;
+                }
+            }
+        }
+
+        unsafe { self.set_len(write) };
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// Removed elements are drop in place and remaining ones are compacted
+    /// at beginning of the segment, keeping their order.
+    ///
+    /// Pages are prefetched ahead of scan position, so faulting cold pages overlaps
+    /// with calls to `f`.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let len = self.len;
+
+        // If `f` or drop panic, remaining elements will be leaked
+        // instead of being drop twice.
+        unsafe { self.set_len(0) };
+
+        let block_len = prefetch_block_len::<T>();
+        let mut write = 0;
+        for read in 0..len {
+            unsafe {
+                if read % block_len == 0 {
+                    prefetch_elements(self.addr, read, read.saturating_add(2 * block_len).min(len));
+                }
+                let read_ptr = self.addr.add(read);
+
+                if f(&*read_ptr) {
+                    if read != write {
+                        ptr::copy_nonoverlapping(read_ptr, self.addr.add(write), 1);
+                    }
+                    write += 1;
+                } else {
+                    ptr::drop_in_place(read_ptr);
+                }
+            }
+        }
+
+        unsafe { self.set_len(write) };
     }
 
     /// Clears the segment, removing all values.
     #[inline]
     pub fn clear(&mut self) {
+        let len = self.len;
         unsafe {
-            let items = slice::from_raw_parts_mut(self.addr, self.len);
             self.set_len(0);
-            ptr::drop_in_place(items);
+            if len > 0 {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.addr, len));
+            }
         }
     }
 
     /// Forces the length of the segment to `new_len`.
+    ///
+    /// No element is initialized or dropped. See `MmapVec::resize` for a safe alternative.
     #[allow(clippy::missing_safety_doc)]
     #[inline(always)]
     pub unsafe fn set_len(&mut self, new_len: usize) {
@@ -134,7 +336,7 @@ impl<T> Segment<T> {
     /// If the segment is already full, value will be return in `Err`.
     #[inline]
     pub fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
-        if self.len == self.capacity {
+        if self.is_full() {
             return Err(value);
         }
 
@@ -147,6 +349,44 @@ impl<T> Segment<T> {
         Ok(())
     }
 
+    /// Add new element to the segment, without checking capacity.
+    ///
+    /// # Safety
+    ///
+    /// Segment must not be full.
+    #[inline(always)]
+    pub(crate) unsafe fn push_unchecked(&mut self, value: T) {
+        debug_assert!(self.len < self.capacity);
+        ptr::write(self.addr.add(self.len), value);
+        self.len += 1;
+    }
+
+    /// Try to insert new element at position `index`, shifting all elements after it.
+    ///
+    /// If the segment is already full, value will be return in `Err`.
+    ///
+    /// Will panic if `index > len`.
+    pub fn insert_within_capacity(&mut self, index: usize, value: T) -> Result<(), T> {
+        let len = self.len;
+        assert!(
+            index <= len,
+            "insertion index (is {index}) should be <= len (is {len})"
+        );
+
+        if len == self.capacity {
+            return Err(value);
+        }
+
+        unsafe {
+            let dst = self.addr.add(index);
+            ptr::copy(dst, dst.add(1), len - index);
+            ptr::write(dst, value);
+        }
+
+        self.len += 1;
+        Ok(())
+    }
+
     /// Remove last element of the segment and reduce its capacity.
     ///
     /// Value will be return if segment is not empty.
@@ -200,6 +440,54 @@ impl<T> Segment<T> {
         };
     }
 
+    /// Flush segment content to disk.
+    ///
+    /// This function is only a wrapper above `libc::msync` with `MS_SYNC` flag,
+    /// so it blocks until data are written.
+    pub fn flush(&self) -> io::Result<()> {
+        if self.addr.is_null() {
+            return Ok(());
+        }
+
+        unsafe { msync(self.addr.cast(), self.disk_size()) }
+    }
+
+    /// Change segment mapping protection, `writable` or read only.
+    ///
+    /// Caller must not write to a read only segment, this includes dropping
+    /// elements with a `Drop` implementation.
+    pub(crate) unsafe fn set_writable(&self, writable: bool) -> io::Result<()> {
+        if self.addr.is_null() {
+            return Ok(());
+        }
+
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+        if libc::mprotect(self.addr.cast(), self.disk_size(), prot) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Get virtual address range occupied by this segment.
+    ///
+    /// Returned tuple is `(start, end)` with `end` excluded.
+    /// This is useful to match segment with `/proc/self/maps` or `pmap` output.
+    ///
+    /// Return `None` for null segment.
+    #[inline]
+    pub fn addr_range(&self) -> Option<(usize, usize)> {
+        if self.addr.is_null() {
+            return None;
+        }
+
+        let start = self.addr as usize;
+        Some((start, start + self.disk_size()))
+    }
+
     /// Inform the kernel that the complete segment will be access in a near future.
     ///
     /// All underlying pages should be load in RAM.
@@ -227,6 +515,14 @@ impl<T> Segment<T> {
         );
     }
 
+    /// Same as `advice_prefetch_all_pages`, but errors are ignored.
+    ///
+    /// Used where prefetching is only an optimization, and must never panic.
+    #[inline]
+    pub(crate) fn advice_prefetch_best_effort(&self) {
+        unsafe { prefetch_elements(self.addr, 0, self.len) };
+    }
+
     /// Inform the kernel that underlying page for `index` will be access in a near future.
     ///
     /// This function is only a wrapper above `libc::madvise`.
@@ -252,6 +548,397 @@ impl<T> Segment<T> {
             io::Error::last_os_error()
         );
     }
+
+    /// Inform the kernel that underlying pages for elements in `range` will be access in a near future.
+    ///
+    /// Range is clamped to segment len.
+    ///
+    /// Will panic if `libc::madvise` return an error.
+    pub fn advice_prefetch_range(&self, range: Range<usize>) {
+        let end = range.end.min(self.len);
+        if self.addr.is_null() || range.start >= end {
+            return;
+        }
+
+        let page_size = page_size();
+        let page_mask = !(page_size.wrapping_add_signed(-1));
+
+        let start_addr = unsafe { self.addr.add(range.start) } as usize & page_mask;
+        let end_addr = unsafe { self.addr.add(end) } as usize;
+
+        let madvise_code = unsafe {
+            libc::madvise(
+                start_addr as *mut libc::c_void,
+                end_addr - start_addr,
+                libc::MADV_WILLNEED,
+            )
+        };
+        assert_eq!(
+            madvise_code,
+            0,
+            "madvise error: {}",
+            io::Error::last_os_error()
+        );
+    }
+
+    /// Inform the kernel that underlying pages for indexes `start`, `start + stride`, ...
+    /// (`count` indexes in total) will be access in a near future.
+    ///
+    /// Consecutive indexes sharing the same page only trigger a single call to `libc::madvise`.
+    /// Indexes out of segment bounds are ignored.
+    ///
+    /// Will panic if `libc::madvise` return an error.
+    pub fn advice_prefetch_strided(&self, start: usize, stride: usize, count: usize) {
+        if self.addr.is_null() {
+            return;
+        }
+
+        let page_size = page_size();
+        let page_mask = !(page_size.wrapping_add_signed(-1));
+        let mut last_page = None;
+
+        for k in 0..count {
+            let index = match k.checked_mul(stride).and_then(|x| x.checked_add(start)) {
+                Some(index) if index < self.len => index,
+                _ => break,
+            };
+
+            let page = unsafe { self.addr.add(index) } as usize & page_mask;
+            if last_page == Some(page) {
+                continue;
+            }
+            last_page = Some(page);
+
+            let madvise_code =
+                unsafe { libc::madvise(page as *mut libc::c_void, page_size, libc::MADV_WILLNEED) };
+            assert_eq!(
+                madvise_code,
+                0,
+                "madvise error: {}",
+                io::Error::last_os_error()
+            );
+
+            if stride == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Inform the kernel how the complete segment mapping will be access.
+    ///
+    /// Advice applies to the whole mapping, including capacity not used yet.
+    ///
+    /// This function is only a wrapper above `libc::madvise`.
+    ///
+    /// Will panic if `libc::madvise` return an error.
+    pub fn advise(&self, pattern: AccessPattern) {
+        if self.addr.is_null() {
+            return;
+        }
+
+        let addr = self.addr.cast();
+        let size = self.disk_size();
+        let madvise_code = unsafe {
+            match pattern {
+                AccessPattern::WillNeed => libc::madvise(addr, size, libc::MADV_WILLNEED),
+                AccessPattern::DontNeed => libc::madvise(addr, size, libc::MADV_DONTNEED),
+                AccessPattern::Sequential => libc::madvise(addr, size, libc::MADV_SEQUENTIAL),
+                AccessPattern::Random => libc::madvise(addr, size, libc::MADV_RANDOM),
+            }
+        };
+        assert_eq!(
+            madvise_code,
+            0,
+            "madvise error: {}",
+            io::Error::last_os_error()
+        );
+    }
+
+    /// Ask the kernel to back the segment with transparent huge pages.
+    ///
+    /// This is best effort: it only has an effect on Linux with THP enabled for
+    /// the underlying file system, and errors are ignored.
+    pub(crate) fn advice_hugepage(&self) {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if !self.addr.is_null() {
+            unsafe { libc::madvise(self.addr.cast(), self.disk_size(), libc::MADV_HUGEPAGE) };
+        }
+    }
+
+    /// Ask the kernel not to map segment in child processes created by `fork`.
+    ///
+    /// This is best effort: it is a no-op outside Linux / Android, and errors are ignored.
+    pub(crate) fn advice_dont_fork(&self) {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if !self.addr.is_null() {
+            unsafe { libc::madvise(self.addr.cast(), self.disk_size(), libc::MADV_DONTFORK) };
+        }
+    }
+
+    /// Interleave segment pages across all NUMA nodes the process is allowed to use.
+    ///
+    /// This is best effort: it is a no-op outside Linux, and errors are ignored.
+    #[cfg(feature = "numa")]
+    pub(crate) fn advice_numa_interleave(&self) {
+        #[cfg(target_os = "linux")]
+        if !self.addr.is_null() {
+            unsafe { mbind_interleave(self.addr.cast(), self.disk_size()) };
+        }
+    }
+
+    /// Inform the kernel that pages covering elements in `range` are not needed for now.
+    ///
+    /// Only pages fully contained in the range are released, and range is clamped to
+    /// segment capacity.
+    ///
+    /// Pages are released with `libc::MADV_DONTNEED`, like `advise(AccessPattern::DontNeed)`
+    /// does for the whole segment. `MADV_FREE` is not used: kernel only supports it for
+    /// private anonymous mappings, and fails with `EINVAL` on segment ones.
+    ///
+    /// Since segments are file backed shared mappings, dirty pages are written back
+    /// to the file and dropped from the process page tables: data is never lost and
+    /// remains readable after this call, next access reloads it from the page cache
+    /// (or from disk if it was evicted meanwhile).
+    ///
+    /// Will panic if `libc::madvise` return an error.
+    pub fn advice_free_range(&self, range: Range<usize>) {
+        let end = range.end.min(self.capacity);
+        if self.addr.is_null() || range.start >= end {
+            return;
+        }
+
+        let page_size = page_size();
+        let page_mask = !(page_size.wrapping_add_signed(-1));
+        let base_addr = self.addr as usize;
+
+        // Round start to next page and end to previous page.
+        // Last page is fully owned by this segment, so it can be included.
+        let start_addr =
+            (base_addr + range.start * mem::size_of::<T>() + page_size - 1) & page_mask;
+        let end_addr = if end == self.capacity {
+            (base_addr + self.disk_size() + page_size - 1) & page_mask
+        } else {
+            (base_addr + end * mem::size_of::<T>()) & page_mask
+        };
+
+        if start_addr >= end_addr {
+            return;
+        }
+
+        let madvise_code = unsafe {
+            libc::madvise(
+                start_addr as *mut _,
+                end_addr - start_addr,
+                libc::MADV_DONTNEED,
+            )
+        };
+        assert_eq!(
+            madvise_code,
+            0,
+            "madvise error: {}",
+            io::Error::last_os_error()
+        );
+    }
+
+    /// Move segment data to a new file at `new_path`, keeping the same capacity.
+    ///
+    /// Elements are copied to the new file, which is flushed and mapped, then the old
+    /// mapping is released. Since segment does not know its backing file, old file is
+    /// left untouched and has to be removed by the caller.
+    ///
+    /// On error, segment still uses its current mapping.
+    ///
+    /// ```rust
+    /// # use mmap_vec::Segment;
+    /// let mut s = Segment::<i32>::open_rw("test_remap_to_1.seg", 2).unwrap();
+    /// s.push_within_capacity(7).unwrap();
+    ///
+    /// s.remap_to("test_remap_to_2.seg").unwrap();
+    /// assert_eq!(&s[..], [7]);
+    /// assert_eq!(s.capacity(), 2);
+    /// # let _ = std::fs::remove_file("test_remap_to_1.seg");
+    /// # let _ = std::fs::remove_file("test_remap_to_2.seg");
+    /// ```
+    pub fn remap_to<P: AsRef<Path>>(&mut self, new_path: P) -> io::Result<()> {
+        let new_segment = unsafe { self.copy_to_file(new_path.as_ref(), self.capacity)? };
+
+        let mut old_segment = mem::replace(self, new_segment);
+        // Elements are now owned by new segment.
+        unsafe { old_segment.set_len(0) };
+
+        Ok(())
+    }
+
+    /// Create a segment of `capacity` elements at `path`, with a bitwise copy of
+    /// segment elements, and flush it.
+    ///
+    /// # Safety
+    ///
+    /// Returned segment has the same len, so elements are owned twice:
+    /// caller must set len of one of the segments to 0.
+    pub(crate) unsafe fn copy_to_file(&self, path: &Path, capacity: usize) -> io::Result<Self> {
+        debug_assert!(capacity >= self.len);
+
+        let mut segment = Self::open_rw(path, capacity)?;
+        if self.len > 0 {
+            ptr::copy_nonoverlapping(self.addr, segment.addr, self.len);
+            segment.set_len(self.len);
+        }
+
+        if let Err(err) = segment.flush() {
+            segment.set_len(0);
+            return Err(MmapVecError::io_with_path(path, err));
+        }
+
+        Ok(segment)
+    }
+
+    /// Count pages of the segment currently resident in RAM.
+    ///
+    /// This function is only a wrapper above `libc::mincore`.
+    /// Null segment has no resident page.
+    pub fn resident_pages(&self) -> io::Result<usize> {
+        if self.addr.is_null() {
+            return Ok(0);
+        }
+
+        let page_size = page_size();
+        let page_count = (self.disk_size() + page_size - 1) / page_size;
+        let mut pages = vec![0u8; page_count];
+
+        let code = unsafe {
+            libc::mincore(
+                self.addr.cast(),
+                self.disk_size(),
+                pages.as_mut_ptr().cast(),
+            )
+        };
+        if code != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(pages.iter().filter(|page| *page & 1 != 0).count())
+    }
+
+    /// Fill every byte of capacity from element `start` to segment end with `byte`.
+    ///
+    /// Caller must make sure no live element is stored there.
+    pub(crate) fn poison_from(&mut self, start: usize, byte: u8) {
+        if self.addr.is_null() || start >= self.capacity {
+            return;
+        }
+
+        debug_assert!(start >= self.len);
+        unsafe {
+            ptr::write_bytes(
+                self.addr.add(start).cast::<u8>(),
+                byte,
+                (self.capacity - start) * mem::size_of::<T>(),
+            )
+        };
+    }
+
+    /// Grow segment to `capacity` by resizing file at `path`, then current mapping with `mremap`.
+    ///
+    /// Unlike mapping file again, data is never touched: kernel only moves page tables
+    /// if mapping cannot be extended in place.
+    ///
+    /// Errors resizing the file are returned. If `mremap` fails, `false` is returned
+    /// instead: segment is left unchanged but file has been extended.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn remap_grow(&mut self, path: &Path, capacity: usize) -> io::Result<bool> {
+        debug_assert!(!self.addr.is_null());
+        debug_assert_eq!(self.guard_size, 0);
+        debug_assert!(capacity > self.capacity);
+
+        let with_path = |err| MmapVecError::io_with_path(path, err);
+        let new_size = segment_size::<T>(capacity)?;
+
+        let file = Fs::open(path).map_err(with_path)?;
+        unsafe { ftruncate::<T>(&file, capacity) }.map_err(with_path)?;
+
+        match retry_on_eintr(|| unsafe { Fs::mremap(self.addr.cast(), self.disk_size(), new_size) })
+        {
+            Ok(addr) => {
+                self.addr = addr.cast();
+                self.capacity = capacity;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Load in RAM pages covering capacity from element `start` to segment end.
+    ///
+    /// Pages are first advised with `libc::MADV_WILLNEED`, then touched one by one
+    /// (read and write back the same byte), so next writes do not page fault.
+    /// Data is left unchanged.
+    pub(crate) fn prefault_from(&mut self, start: usize) -> io::Result<()> {
+        if self.addr.is_null() || start >= self.capacity {
+            return Ok(());
+        }
+
+        let page_size = page_size();
+        let page_mask = !(page_size.wrapping_add_signed(-1));
+        let base_addr = self.addr as usize;
+
+        let start_addr = base_addr + start * mem::size_of::<T>();
+        let end_addr = base_addr + self.disk_size();
+
+        let madvise_code = unsafe {
+            libc::madvise(
+                (start_addr & page_mask) as *mut libc::c_void,
+                end_addr - (start_addr & page_mask),
+                libc::MADV_WILLNEED,
+            )
+        };
+        if madvise_code != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut addr = start_addr;
+        while addr < end_addr {
+            unsafe {
+                let byte = addr as *mut u8;
+                ptr::write_volatile(byte, ptr::read_volatile(byte));
+            }
+            addr = (addr & page_mask) + page_size;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> Segment<T> {
+    /// Pointer to segment data, never null even for null segment.
+    ///
+    /// `slice::from_raw_parts` requires a non null pointer even for empty slices,
+    /// so null segment uses a dangling (but aligned) one instead.
+    ///
+    /// Used to build slices, so it also checks that `len` is covered by the mapping.
+    #[inline(always)]
+    fn data_ptr(&self) -> *mut T {
+        debug_assert!(self.len <= self.capacity);
+        debug_assert!(!self.addr.is_null() || self.len == 0);
+
+        if self.addr.is_null() {
+            NonNull::dangling().as_ptr()
+        } else {
+            self.addr
+        }
+    }
+}
+
+impl<T> fmt::Debug for Segment<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Segment")
+            .field("addr", &self.addr)
+            .field("len", &self.len)
+            .field("capacity", &self.capacity)
+            .field("guard_size", &self.guard_size)
+            .field("addr_range", &self.addr_range())
+            .finish()
+    }
 }
 
 impl<T> Deref for Segment<T> {
@@ -259,14 +946,14 @@ impl<T> Deref for Segment<T> {
 
     #[inline(always)]
     fn deref(&self) -> &Self::Target {
-        unsafe { slice::from_raw_parts(self.addr, self.len) }
+        unsafe { slice::from_raw_parts(self.data_ptr(), self.len) }
     }
 }
 
 impl<T> DerefMut for Segment<T> {
     #[inline(always)]
     fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { slice::from_raw_parts_mut(self.addr, self.len) }
+        unsafe { slice::from_raw_parts_mut(self.data_ptr(), self.len) }
     }
 }
 
@@ -277,7 +964,8 @@ impl<T> Drop for Segment<T> {
         }
 
         if !self.addr.is_null() {
-            let _ = unsafe { munmap(self.addr, self.capacity) };
+            let size = mapping_size(self.disk_size(), self.guard_size);
+            let _ = unsafe { munmap(self.addr, size) };
         }
     }
 }
@@ -285,57 +973,142 @@ impl<T> Drop for Segment<T> {
 unsafe impl<T> Send for Segment<T> {}
 unsafe impl<T> Sync for Segment<T> {}
 
-unsafe fn ftruncate<T>(file: &File, capacity: usize) -> io::Result<()> {
+/// Compute segment size in bytes, checking it fits in address space.
+pub(crate) fn segment_size<T>(capacity: usize) -> io::Result<usize> {
     check_zst::<T>();
-    let segment_size = capacity * mem::size_of::<T>();
-    let fd = file.as_raw_fd();
+    let element_size = mem::size_of::<T>();
+
+    match capacity.checked_mul(element_size) {
+        Some(size) if size <= isize::MAX as usize => Ok(size),
+        _ => Err(MmapVecError::CapacityOverflow {
+            capacity,
+            element_size,
+        }
+        .into()),
+    }
+}
+
+unsafe fn ftruncate<T>(file: &File, capacity: usize) -> io::Result<()> {
+    let segment_size = segment_size::<T>(capacity)?;
 
-    if libc::ftruncate(fd, segment_size as libc::off_t) != 0 {
+    retry_on_eintr(|| Fs::ftruncate(file, segment_size)).map_err(|err| {
         COUNT_FTRUNCATE_FAILED.fetch_add(1, Ordering::Relaxed);
-        Err(io::Error::last_os_error())
-    } else {
-        Ok(())
+        err
+    })
+}
+
+/// Size of the whole mapping: segment data, then guard pages starting on next page.
+fn mapping_size(segment_size: usize, guard_size: usize) -> usize {
+    if guard_size == 0 {
+        return segment_size;
     }
+
+    let page_mask = !(page_size().wrapping_add_signed(-1));
+    ((segment_size + page_size() - 1) & page_mask) + guard_size
 }
 
-unsafe fn mmap<T>(file: &File, capacity: usize) -> io::Result<*mut T> {
-    check_zst::<T>();
-    let segment_size = capacity * mem::size_of::<T>();
-
-    // It is safe to not keep a reference to the initial file descriptor.
-    // See: https://stackoverflow.com/questions/17490033/do-i-need-to-keep-a-file-open-after-calling-mmap-on-it
-    let fd = file.as_raw_fd();
-
-    let addr = libc::mmap(
-        std::ptr::null_mut(),
-        segment_size as libc::size_t,
-        libc::PROT_READ | libc::PROT_WRITE,
-        libc::MAP_SHARED,
-        fd,
-        0,
+unsafe fn mmap<T>(file: &File, capacity: usize, guard_size: usize) -> io::Result<*mut T> {
+    let segment_size = segment_size::<T>(capacity)?;
+    let size = mapping_size(segment_size, guard_size);
+
+    match retry_on_eintr(|| Fs::mmap(file, size)) {
+        Ok(addr) => {
+            COUNT_ACTIVE_SEGMENT.fetch_add(1, Ordering::Relaxed);
+
+            if guard_size > 0 {
+                let guard_addr = addr.cast::<u8>().add(size - guard_size);
+                if libc::mprotect(guard_addr.cast(), guard_size, libc::PROT_NONE) != 0 {
+                    let err = io::Error::last_os_error();
+                    let _ = munmap(addr, size);
+                    return Err(err);
+                }
+            }
+            Ok(addr.cast())
+        }
+        Err(err) => {
+            COUNT_MMAP_FAILED.fetch_add(1, Ordering::Relaxed);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(all(feature = "numa", target_os = "linux"))]
+unsafe fn mbind_interleave(addr: *mut libc::c_void, size: usize) -> libc::c_long {
+    // Not exported by `libc`, see `linux/mempolicy.h`.
+    const MPOL_INTERLEAVE: libc::c_int = 3;
+    const MPOL_F_MEMS_ALLOWED: libc::c_ulong = 1 << 2;
+    const MAX_NODES: usize = 1024;
+
+    let mut nodemask = [0 as libc::c_ulong; MAX_NODES / (8 * mem::size_of::<libc::c_ulong>())];
+    let code = libc::syscall(
+        libc::SYS_get_mempolicy,
+        ptr::null_mut::<libc::c_int>(),
+        nodemask.as_mut_ptr(),
+        MAX_NODES as libc::c_ulong,
+        ptr::null_mut::<libc::c_void>(),
+        MPOL_F_MEMS_ALLOWED,
     );
+    if code != 0 {
+        return code;
+    }
 
-    if addr == libc::MAP_FAILED {
-        COUNT_MMAP_FAILED.fetch_add(1, Ordering::Relaxed);
-        Err(io::Error::last_os_error())
-    } else {
-        COUNT_ACTIVE_SEGMENT.fetch_add(1, Ordering::Relaxed);
-        Ok(addr.cast())
+    libc::syscall(
+        libc::SYS_mbind,
+        addr,
+        size,
+        MPOL_INTERLEAVE,
+        nodemask.as_ptr(),
+        MAX_NODES as libc::c_ulong,
+        0 as libc::c_uint,
+    )
+}
+
+/// Number of elements prefetched at once when scanning a segment in place.
+fn prefetch_block_len<T>() -> usize {
+    (page_size() / mem::size_of::<T>()).max(1) * PREFETCH_AHEAD_PAGES
+}
+
+/// Inform the kernel that pages holding elements `start..end` at `addr` will be access soon.
+///
+/// Used while elements are moved in place, when segment len cannot be trusted.
+/// This is only a hint, so errors are ignored.
+unsafe fn prefetch_elements<T>(addr: *mut T, start: usize, end: usize) {
+    if start >= end {
+        return;
     }
+
+    let page_mask = !(page_size().wrapping_add_signed(-1));
+    let start_addr = addr.add(start) as usize & page_mask;
+    let end_addr = addr.add(end) as usize;
+    libc::madvise(
+        start_addr as *mut libc::c_void,
+        end_addr - start_addr,
+        libc::MADV_WILLNEED,
+    );
 }
 
-unsafe fn munmap<T>(addr: *mut T, capacity: usize) -> io::Result<()> {
-    check_zst::<T>();
-    debug_assert!(!addr.is_null());
-    debug_assert!(capacity > 0);
+pub(crate) unsafe fn msync(addr: *mut libc::c_void, size: usize) -> io::Result<()> {
+    retry_on_eintr(|| {
+        if libc::msync(addr, size, libc::MS_SYNC) != 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    })
+}
 
-    let unmap_code = libc::munmap(addr.cast(), capacity * mem::size_of::<T>());
+unsafe fn munmap<T>(addr: *mut T, size: usize) -> io::Result<()> {
+    debug_assert!(!addr.is_null());
+    debug_assert!(size > 0);
 
-    if unmap_code != 0 {
-        COUNT_MUNMAP_FAILED.fetch_add(1, Ordering::Relaxed);
-        Err(io::Error::last_os_error())
-    } else {
-        COUNT_ACTIVE_SEGMENT.fetch_sub(1, Ordering::Relaxed);
-        Ok(())
+    match retry_on_eintr(|| Fs::munmap(addr.cast(), size)) {
+        Ok(()) => {
+            COUNT_ACTIVE_SEGMENT.fetch_sub(1, Ordering::Relaxed);
+            Ok(())
+        }
+        Err(err) => {
+            COUNT_MUNMAP_FAILED.fetch_add(1, Ordering::Relaxed);
+            Err(err)
+        }
     }
 }
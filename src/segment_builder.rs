@@ -43,6 +43,52 @@ impl DefaultSegmentBuilder {
     pub fn create_dir_all(&self) -> io::Result<()> {
         fs::create_dir_all(self.store_path.as_ref())
     }
+
+    /// Init struct with `primary` folder, or with `fallback` one if `primary` is not writable.
+    ///
+    /// Folders are created if needed, then checked by writing and removing a probe file.
+    /// An error describing both failures is returned if no folder is writable.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::{DefaultSegmentBuilder, SegmentBuilder};
+    /// let builder = DefaultSegmentBuilder::with_fallback("/dev/null/mmap-vec", std::env::temp_dir())
+    ///     .unwrap();
+    /// assert!(builder.new_segment_path().starts_with(std::env::temp_dir()));
+    /// ```
+    pub fn with_fallback<P1, P2>(primary: P1, fallback: P2) -> io::Result<Self>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        let primary = Self::with_path(primary);
+        let primary_err = match primary.check_writable() {
+            Ok(()) => return Ok(primary),
+            Err(err) => err,
+        };
+
+        let fallback = Self::with_path(fallback);
+        match fallback.check_writable() {
+            Ok(()) => Ok(fallback),
+            Err(fallback_err) => Err(io::Error::new(
+                fallback_err.kind(),
+                format!(
+                    "no writable store folder: {}: {primary_err}, {}: {fallback_err}",
+                    primary.store_path.display(),
+                    fallback.store_path.display(),
+                ),
+            )),
+        }
+    }
+
+    /// Create store folder and check a file can be written in it.
+    fn check_writable(&self) -> io::Result<()> {
+        self.create_dir_all()?;
+
+        let probe_path = self.new_segment_path().with_extension("probe");
+        fs::write(&probe_path, b"")?;
+        fs::remove_file(&probe_path)
+    }
 }
 
 impl Default for DefaultSegmentBuilder {
@@ -81,4 +127,29 @@ mod tests {
         let path2 = builder.new_segment_path();
         assert_ne!(path1, path2);
     }
+
+    #[test]
+    fn test_with_fallback() {
+        let tmp = env::temp_dir();
+
+        // Primary is writable
+        let primary = tmp.join("test_segment_builder_primary");
+        let builder =
+            DefaultSegmentBuilder::with_fallback(&primary, "/dev/null/fallback").expect("no dir");
+        assert!(builder.new_segment_path().starts_with(&primary));
+        assert!(primary.is_dir());
+
+        // Primary cannot be created
+        let builder =
+            DefaultSegmentBuilder::with_fallback("/dev/null/primary", &tmp).expect("no dir");
+        assert!(builder.new_segment_path().starts_with(&tmp));
+
+        // Both fail
+        let err = DefaultSegmentBuilder::with_fallback("/dev/null/primary", "/dev/null/fallback")
+            .expect_err("should fail");
+        let msg = err.to_string();
+        assert!(msg.starts_with("no writable store folder"));
+        assert!(msg.contains("/dev/null/primary"));
+        assert!(msg.contains("/dev/null/fallback"));
+    }
 }
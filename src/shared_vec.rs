@@ -0,0 +1,123 @@
+use std::{io, ops::Deref, path::PathBuf, sync::Arc};
+
+use crate::{DefaultSegmentBuilder, MmapVec, SegmentBuilder};
+
+/// A read only disk memory mapped vector that can be cheaply shared.
+///
+/// Created by `MmapVec::into_shared`.
+///
+/// # How it works ?
+///
+/// Source vec segment is protected as read only, then moved behind an `Arc`.
+/// Cloning a shared vec only clones the `Arc`: every clone reads the same mapping.
+///
+/// Data can be modified again after an explicit copy on write:
+/// - `make_mut` copies content into a private writable vec,
+/// - `into_mut` does the same, but reuses the segment without any copy when
+///   this is the last clone.
+///
+/// Backing file is removed when last clone is dropped.
+///
+/// Only `Copy` types are supported, since elements cannot be dropped in place
+/// from a read only mapping.
+///
+/// Example:
+/// ```rust
+/// # use mmap_vec::MmapVec;
+/// let v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+/// let shared = v.into_shared().unwrap();
+///
+/// let reader = shared.clone();
+/// std::thread::spawn(move || assert_eq!(reader.iter().sum::<u64>(), 6))
+///     .join()
+///     .unwrap();
+///
+/// let mut v = shared.make_mut().unwrap();
+/// v.push(4).unwrap();
+/// assert_eq!(&v[..], [1, 2, 3, 4]);
+/// assert_eq!(&shared[..], [1, 2, 3]);
+/// ```
+#[derive(Debug)]
+pub struct SharedMmapVec<T: Copy, B: SegmentBuilder = DefaultSegmentBuilder> {
+    inner: Arc<MmapVec<T, B>>,
+}
+
+impl<T, B> SharedMmapVec<T, B>
+where
+    T: Copy,
+    B: SegmentBuilder,
+{
+    /// Protect `vec` segment as read only and share it.
+    pub(crate) fn new(vec: MmapVec<T, B>) -> io::Result<Self> {
+        unsafe { vec.segment.set_writable(false)? };
+        Ok(Self {
+            inner: Arc::new(vec),
+        })
+    }
+
+    /// Number of clones sharing the same mapping, including this one.
+    #[inline(always)]
+    pub fn share_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+
+    /// Path of the shared backing file.
+    #[inline(always)]
+    pub fn path(&self) -> PathBuf {
+        self.inner.path()
+    }
+
+    /// Copy content into a new private writable vec.
+    ///
+    /// Like `MmapVec::try_clone`, a new backing file is created, and shared vec is left untouched.
+    #[inline(always)]
+    pub fn make_mut(&self) -> io::Result<MmapVec<T, B>>
+    where
+        B: Clone,
+    {
+        self.inner.try_clone()
+    }
+
+    /// Get back a writable vec.
+    ///
+    /// If this is the last clone, segment is made writable again and reused without copy.
+    /// Otherwise, content is copied using `make_mut`.
+    pub fn into_mut(self) -> io::Result<MmapVec<T, B>>
+    where
+        B: Clone,
+    {
+        match Arc::try_unwrap(self.inner) {
+            Ok(vec) => {
+                unsafe { vec.segment.set_writable(true)? };
+                Ok(vec)
+            }
+            Err(inner) => inner.try_clone(),
+        }
+    }
+}
+
+impl<T, B> Clone for SharedMmapVec<T, B>
+where
+    T: Copy,
+    B: SegmentBuilder,
+{
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T, B> Deref for SharedMmapVec<T, B>
+where
+    T: Copy,
+    B: SegmentBuilder,
+{
+    type Target = [T];
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
@@ -7,6 +7,7 @@ pub(crate) static COUNT_ACTIVE_SEGMENT: AtomicU64 = AtomicU64::new(0);
 pub(crate) static COUNT_FTRUNCATE_FAILED: AtomicU64 = AtomicU64::new(0);
 pub(crate) static COUNT_MMAP_FAILED: AtomicU64 = AtomicU64::new(0);
 pub(crate) static COUNT_MUNMAP_FAILED: AtomicU64 = AtomicU64::new(0);
+pub(crate) static COUNT_VEC_GROW: AtomicU64 = AtomicU64::new(0);
 
 /// Provides few statistics about low level segment allocation.
 ///
@@ -22,6 +23,7 @@ impl fmt::Debug for MmapStats {
             .field("ftruncate_failed", &self.ftruncate_failed())
             .field("map_failed", &self.map_failed())
             .field("unmap_failed", &self.unmap_failed())
+            .field("vec_grows", &self.vec_grows())
             .finish()
     }
 }
@@ -55,4 +57,13 @@ impl MmapStats {
     pub fn unmap_failed(&self) -> u64 {
         COUNT_MUNMAP_FAILED.load(Ordering::Relaxed)
     }
+
+    /// Get number of times a vec segment has been remapped to grow.
+    ///
+    /// Unlike `active_segment`, this is not affected by the temporary double mapping
+    /// done while growing, and calls to `reserve` that do not need to grow are not counted.
+    #[inline(always)]
+    pub fn vec_grows(&self) -> u64 {
+        COUNT_VEC_GROW.load(Ordering::Relaxed)
+    }
 }
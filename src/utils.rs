@@ -1,7 +1,140 @@
-use std::mem;
+use std::{
+    ffi::CString,
+    fs::File,
+    io::{self, Read},
+    mem,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
+use crate::error::MmapVecError;
+
+/// Page size, cached after first call (0 when not yet known).
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Memory page size of the system.
+///
+/// Only first call asks `sysconf`, next ones are a single atomic load.
+#[inline]
 pub fn page_size() -> usize {
-    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+    match PAGE_SIZE.load(Ordering::Relaxed) {
+        0 => {
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+            PAGE_SIZE.store(page_size, Ordering::Relaxed);
+            page_size
+        }
+        page_size => page_size,
+    }
+}
+
+/// Round `desired` capacity to the capacity `MmapVec::reserve` will actually allocate.
+///
+/// Capacity is rounded up to the next multiple of element count that fit in a page.
+/// If `T` is bigger than a page, capacity is returned as is.
+/// Result saturates at `usize::MAX`.
+///
+/// ```rust
+/// # use mmap_vec::round_capacity_to_page;
+/// let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+/// let per_page = page_size / 4;
+///
+/// assert_eq!(round_capacity_to_page::<u32>(50), per_page);
+/// assert_eq!(round_capacity_to_page::<u32>(per_page + 1), 2 * per_page);
+/// ```
+pub fn round_capacity_to_page<T>(desired: usize) -> usize {
+    check_zst::<T>();
+    let page_capacity = page_size() / mem::size_of::<T>();
+    if page_capacity == 0 || desired % page_capacity == 0 {
+        desired
+    } else {
+        desired.saturating_add(page_capacity - (desired % page_capacity))
+    }
+}
+
+/// Call `f` again as long as it fails with `io::ErrorKind::Interrupted`.
+///
+/// Syscalls can be interrupted by signals (timers, profilers, ...) without
+/// anything being wrong.
+pub fn retry_on_eintr<T, F>(mut f: F) -> io::Result<T>
+where
+    F: FnMut() -> io::Result<T>,
+{
+    loop {
+        match f() {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            result => return result,
+        }
+    }
+}
+
+/// Check whether files at `a` and `b` have exactly the same content.
+///
+/// Sizes are compared first, then content is streamed from both files by blocks.
+/// Two empty files are equal. Useful to check a copy made with `MmapVec::snapshot_to`
+/// still matches vec backing file, once flushed.
+///
+/// Returned errors wrap a `MmapVecError::FileIo` recording failing path.
+///
+/// ```rust
+/// # use mmap_vec::{files_equal, MmapVec};
+/// let v = MmapVec::<u32>::try_from([1, 2, 3]).unwrap();
+/// let dest = std::env::temp_dir().join("mmap_vec_files_equal_example.seg");
+/// v.snapshot_to(&dest).unwrap();
+///
+/// let other = MmapVec::<u32>::try_from([1, 2, 3]).unwrap();
+/// let other_dest = std::env::temp_dir().join("mmap_vec_files_equal_example_other.seg");
+/// other.snapshot_to(&other_dest).unwrap();
+///
+/// assert!(files_equal(&dest, &other_dest).unwrap());
+/// # std::fs::remove_file(&dest).unwrap();
+/// # std::fs::remove_file(&other_dest).unwrap();
+/// ```
+pub fn files_equal<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> io::Result<bool> {
+    const BLOCK_SIZE: usize = 64 * 1024;
+
+    let (a, b) = (a.as_ref(), b.as_ref());
+    let with_path_a = |err| MmapVecError::io_with_path(a, err);
+    let with_path_b = |err| MmapVecError::io_with_path(b, err);
+
+    let mut file_a = File::open(a).map_err(with_path_a)?;
+    let mut file_b = File::open(b).map_err(with_path_b)?;
+
+    let size = file_a.metadata().map_err(with_path_a)?.len();
+    if file_b.metadata().map_err(with_path_b)?.len() != size {
+        return Ok(false);
+    }
+
+    let mut block_a = vec![0; BLOCK_SIZE];
+    let mut block_b = vec![0; BLOCK_SIZE];
+    let mut remaining = size;
+    while remaining > 0 {
+        let block_len = remaining.min(BLOCK_SIZE as u64) as usize;
+        let (block_a, block_b) = (&mut block_a[..block_len], &mut block_b[..block_len]);
+
+        file_a.read_exact(block_a).map_err(with_path_a)?;
+        file_b.read_exact(block_b).map_err(with_path_b)?;
+        if block_a != block_b {
+            return Ok(false);
+        }
+        remaining -= block_len as u64;
+    }
+    Ok(true)
+}
+
+/// Number of bytes available to unprivileged users on file system holding `dir`.
+pub fn available_space(dir: &Path) -> io::Result<u64> {
+    let dir = CString::new(dir.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    let mut stats = mem::MaybeUninit::<libc::statvfs>::uninit();
+    if unsafe { libc::statvfs(dir.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let stats = unsafe { stats.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stats.f_bavail as u64 * stats.f_frsize as u64)
 }
 
 pub const fn check_zst<T>() {
@@ -9,3 +142,52 @@ pub const fn check_zst<T>() {
         panic!("Zero sized type are not supported with MmapVec. What is the point of mapping ZST to disk ?");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_size() {
+        let expected = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+        assert_eq!(page_size(), expected);
+        assert_eq!(PAGE_SIZE.load(Ordering::Relaxed), expected);
+        assert_eq!(page_size(), expected);
+    }
+
+    #[test]
+    fn test_round_capacity_to_page() {
+        let page_size = page_size();
+
+        assert_eq!(round_capacity_to_page::<u8>(0), 0);
+        assert_eq!(round_capacity_to_page::<u8>(1), page_size);
+        assert_eq!(round_capacity_to_page::<u8>(page_size), page_size);
+        assert_eq!(round_capacity_to_page::<u8>(page_size + 1), 2 * page_size);
+
+        let per_page = page_size / 8;
+        assert_eq!(round_capacity_to_page::<u64>(1), per_page);
+        assert_eq!(round_capacity_to_page::<u64>(per_page), per_page);
+        assert_eq!(round_capacity_to_page::<u64>(per_page + 1), 2 * per_page);
+
+        // Element size not dividing page size
+        let per_page = page_size / 24;
+        assert_eq!(round_capacity_to_page::<[u8; 24]>(1), per_page);
+        assert_eq!(round_capacity_to_page::<[u8; 24]>(per_page), per_page);
+        assert_eq!(
+            round_capacity_to_page::<[u8; 24]>(per_page + 1),
+            2 * per_page
+        );
+
+        // Types of at least a page (64KB is the biggest common page size)
+        assert_eq!(round_capacity_to_page::<[u8; 65536]>(0), 0);
+        assert_eq!(round_capacity_to_page::<[u8; 65536]>(5), 5);
+        assert_eq!(round_capacity_to_page::<[u8; 65537]>(1), 1);
+        assert_eq!(round_capacity_to_page::<[u8; 65537]>(5), 5);
+
+        // Type bigger than a page
+        assert_eq!(round_capacity_to_page::<[u8; 100_000]>(3), 3);
+
+        // Saturate on overflow
+        assert_eq!(round_capacity_to_page::<u64>(usize::MAX), usize::MAX);
+    }
+}
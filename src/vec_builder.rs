@@ -2,9 +2,34 @@ use std::{io, marker::PhantomData, mem};
 
 use crate::{
     utils::{check_zst, page_size},
-    DefaultSegmentBuilder, MmapVec, Segment, SegmentBuilder,
+    DefaultSegmentBuilder, MmapVec, SegmentBuilder,
 };
 
+/// Vec settings chosen with `MmapVecBuilder`.
+///
+/// They are kept by the vec, and carried over to every vec derived from it
+/// (clones, chunks, ...), so new segments are mapped the same way.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct VecConfig {
+    /// Max segment file size in bytes, if any.
+    pub(crate) segment_size_limit: Option<usize>,
+    /// Prefault pages added by a grow.
+    pub(crate) warm_new_capacity: bool,
+    /// Round big segments to huge page size and advise `MADV_HUGEPAGE`.
+    pub(crate) transparent_hugepages: bool,
+    /// Map a `PROT_NONE` guard page after segment data.
+    pub(crate) guard_pages: bool,
+    /// Advise `MADV_DONTFORK` on every mapping.
+    pub(crate) dont_fork: bool,
+    /// Check file system free space before growing.
+    pub(crate) check_free_space: bool,
+    /// Byte written over spare capacity added by a grow, if any.
+    pub(crate) poison_spare: Option<u8>,
+    /// Interleave pages across NUMA nodes.
+    #[cfg(feature = "numa")]
+    pub(crate) numa_interleave: bool,
+}
+
 /// Helps to create vec with custom parameters.
 ///
 /// Example usage:
@@ -23,11 +48,15 @@ use crate::{
 pub struct MmapVecBuilder<T, SB: SegmentBuilder = DefaultSegmentBuilder> {
     segment_builder: SB,
     capacity: usize,
+    config: VecConfig,
     _phantom: PhantomData<T>,
 }
 
 impl<T, SB: SegmentBuilder> MmapVecBuilder<T, SB> {
     /// Create new struct.
+    ///
+    /// Default capacity is `page_size / size_of::<T>()`, so built vec fits in a single
+    /// memory page (4096 bytes on most systems, 16KB on some ARM ones).
     #[inline(always)]
     pub fn new() -> Self {
         Self::default()
@@ -41,24 +70,206 @@ impl<T, SB: SegmentBuilder> MmapVecBuilder<T, SB> {
     }
 
     /// Update capacity.
+    ///
+    /// Built vec is empty, but its first segment already holds `capacity` elements:
+    /// pushing up to `capacity` values never grows it. Use it as a size hint when
+    /// final length is known in advance.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVecBuilder;
+    /// let mut v = MmapVecBuilder::<u64>::new().capacity(10_000).try_build().unwrap();
+    /// assert!(v.is_empty());
+    /// assert_eq!(v.capacity(), 10_000);
+    ///
+    /// for i in 0..10_000 {
+    ///     v.push(i).unwrap();
+    /// }
+    /// assert_eq!(v.capacity(), 10_000);
+    /// ```
+    #[doc(alias = "reserve_hint")]
     #[inline(always)]
     pub fn capacity(mut self, capacity: usize) -> Self {
         self.capacity = capacity;
         self
     }
 
+    /// Update capacity so it fills exactly `pages` memory pages.
+    ///
+    /// Capacity is set to `pages * (page_size / size_of::<T>())`.
+    /// Backing file will be exactly `pages` pages long when `size_of::<T>()` divides
+    /// page size, otherwise end of every page is left unused.
+    ///
+    /// When `T` is bigger than a page, capacity is set to the number of elements
+    /// fitting in `pages * page_size` bytes instead.
+    ///
+    /// Capacity saturates to `usize::MAX` on overflow, so `try_build` fails with
+    /// `MmapVecError::CapacityOverflow`.
+    #[inline(always)]
+    pub fn capacity_pages(mut self, pages: usize) -> Self {
+        let page_size = page_size();
+        let element_size = mem::size_of::<T>();
+        self.capacity = if element_size > page_size {
+            pages
+                .checked_mul(page_size)
+                .map_or(usize::MAX, |bytes| bytes / element_size)
+        } else {
+            pages.saturating_mul(page_size / element_size)
+        };
+        self
+    }
+
+    /// Limit segment file size to `bytes`.
+    ///
+    /// Growing the vec above this limit returns `MmapVecError::SegmentSizeExceeded`.
+    /// Capacity rounding to page size is capped to the limit, so vec can grow up to
+    /// `bytes / size_of::<T>()` elements even if limit is not a multiple of page size.
+    ///
+    /// Example:
+    /// ```rust
+    /// # use mmap_vec::MmapVecBuilder;
+    /// let mut v = MmapVecBuilder::<u64>::new()
+    ///     .capacity(0)
+    ///     .segment_size_limit(100)
+    ///     .try_build()
+    ///     .unwrap();
+    ///
+    /// for i in 0..12 {
+    ///     v.push(i).unwrap();
+    /// }
+    /// assert_eq!(v.capacity(), 12);
+    /// assert!(v.push(12).is_err());
+    /// ```
+    #[inline(always)]
+    pub fn segment_size_limit(mut self, bytes: usize) -> Self {
+        self.config.segment_size_limit = Some(bytes);
+        self
+    }
+
+    /// Load in RAM pages added each time the vec grows.
+    ///
+    /// After a grow remaps the segment, new pages are advised with `MADV_WILLNEED` and
+    /// touched, so first pushes into new capacity do not stall on page faults.
+    /// This makes grow slower but smooths push latency.
+    ///
+    /// Disabled by default.
+    #[inline(always)]
+    pub fn warm_new_capacity(mut self, warm: bool) -> Self {
+        self.config.warm_new_capacity = warm;
+        self
+    }
+
+    /// Use transparent huge pages for big segments.
+    ///
+    /// When vec grows above 2MB, capacity is rounded to a multiple of 2MB instead of
+    /// page size, and segment is advised with `MADV_HUGEPAGE`. This reduces TLB misses
+    /// on huge numeric arrays.
+    ///
+    /// This only helps on Linux, with THP enabled for the file system holding
+    /// segments (`/sys/kernel/mm/transparent_hugepage/`). Elsewhere, only the
+    /// rounding applies.
+    ///
+    /// Disabled by default.
+    #[inline(always)]
+    pub fn transparent_hugepages(mut self, enabled: bool) -> Self {
+        self.config.transparent_hugepages = enabled;
+        self
+    }
+
+    /// Map a `PROT_NONE` guard page just after vec segment.
+    ///
+    /// A stray write past the end of the mapping then faults immediately, rather than
+    /// silently corrupting an adjacent mapping. This is meant to debug unsafe code using
+    /// raw pointers to vec content.
+    ///
+    /// Guard page is added every time the segment is mapped by `try_build`, grows or
+    /// `shrink_to_fit`, and to vecs created from this one (`try_clone`, ...).
+    /// It costs one page of address space per vec, but no disk or RAM.
+    ///
+    /// Disabled by default.
+    #[inline(always)]
+    pub fn guard_pages(mut self, enabled: bool) -> Self {
+        self.config.guard_pages = enabled;
+        self
+    }
+
+    /// Do not share vec mappings with child processes.
+    ///
+    /// Every mapping of the vec is advised with `MADV_DONTFORK`, so `fork` does not have
+    /// to copy its page tables, which keeps spawning subprocesses fast with huge vecs.
+    /// Vec content is then not mapped at all in the child: it must not be accessed
+    /// there (this is fine when child only calls `exec`).
+    ///
+    /// This is a no-op on platforms other than Linux / Android.
+    ///
+    /// Disabled by default.
+    #[inline(always)]
+    pub fn dont_fork(mut self, enabled: bool) -> Self {
+        self.config.dont_fork = enabled;
+        self
+    }
+
+    /// Check file system free space before growing the vec.
+    ///
+    /// Segment files are sparse: growing them always succeeds, but writing to new pages
+    /// crashes the process with `SIGBUS` if the disk is full. When enabled, free space of
+    /// the folder holding the segment is checked with `statvfs` every time the segment is
+    /// mapped with a bigger size, and `MmapVecError::InsufficientSpace` is returned if it
+    /// does not fit.
+    ///
+    /// This is only a best effort check: other processes can still fill the disk later.
+    ///
+    /// Disabled by default.
+    #[inline(always)]
+    pub fn check_free_space(mut self, enabled: bool) -> Self {
+        self.config.check_free_space = enabled;
+        self
+    }
+
+    /// Fill spare capacity with `byte` instead of zeros.
+    ///
+    /// Every time capacity is added (by `try_build` or a grow), new bytes are
+    /// overwritten with `byte` (e.g. `0xAA`). Reading spare capacity that was never
+    /// written, for example after a wrong `assume_init_len`, then gives obviously
+    /// wrong values. Live elements are never touched.
+    ///
+    /// This is meant for debugging: it writes every new page, so it makes grows slower
+    /// and allocates disk blocks that sparse files would otherwise skip.
+    ///
+    /// Disabled by default.
+    #[inline(always)]
+    pub fn poison_spare(mut self, byte: u8) -> Self {
+        self.config.poison_spare = Some(byte);
+        self
+    }
+
+    /// Interleave vec pages across NUMA nodes.
+    ///
+    /// Every mapping of the vec gets a `MPOL_INTERLEAVE` memory policy (using `mbind`)
+    /// over all nodes the process is allowed to use. Pages are then spread round robin
+    /// across nodes, which balances memory bandwidth for huge read mostly arrays scanned
+    /// by threads running on different sockets.
+    ///
+    /// Kernel may ignore this policy for pages of the page cache, depending on the
+    /// file system holding segments (it applies to `tmpfs`).
+    ///
+    /// This is a no-op on platforms other than Linux, and errors are ignored.
+    ///
+    /// Disabled by default.
+    #[cfg(feature = "numa")]
+    #[inline(always)]
+    pub fn numa_interleave(mut self, enabled: bool) -> Self {
+        self.config.numa_interleave = enabled;
+        self
+    }
+
     /// Try building a new vec with given parameter.
     ///
-    /// This function may failed if segment creation failed.
+    /// This function may failed if segment creation failed or if capacity is
+    /// above segment size limit. A missing store folder is reported as
+    /// `MmapVecError::InvalidStoreDir`.
     pub fn try_build(self) -> io::Result<MmapVec<T, SB>> {
-        let path = self.segment_builder.new_segment_path();
-        let segment = Segment::open_rw(&path, self.capacity)?;
-
-        Ok(MmapVec {
-            segment,
-            builder: self.segment_builder,
-            path,
-        })
+        MmapVec::open_with_config(self.segment_builder, self.capacity, self.config)
     }
 }
 
@@ -69,6 +280,7 @@ impl<T, SB: SegmentBuilder> Default for MmapVecBuilder<T, SB> {
         Self {
             segment_builder: SB::default(),
             capacity: page_size() / mem::size_of::<T>(),
+            config: VecConfig::default(),
             _phantom: PhantomData,
         }
     }
@@ -0,0 +1,126 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use mmap_vec::{BufferedMmapVec, MmapVec, MmapVecBuilder};
+
+pub use data_gen::*;
+
+mod data_gen;
+
+#[test]
+fn test_push_and_commit() {
+    let mut v = BufferedMmapVec::<DataRow>::with_batch_len(3);
+    assert_eq!(v.batch_len(), 3);
+    assert!(v.is_empty());
+
+    v.push(ROW1).unwrap();
+    v.push(ROW2).unwrap();
+    assert_eq!(v.len(), 2);
+    assert_eq!(v.pending_len(), 2);
+    assert!(v.committed().is_empty());
+    assert_eq!(v.committed().capacity(), 0);
+
+    // Buffer is committed once full
+    v.push(ROW3).unwrap();
+    assert_eq!(v.pending_len(), 0);
+    assert_eq!(&v.committed()[..], [ROW1, ROW2, ROW3]);
+
+    // Explicit commit
+    v.push(ROW4).unwrap();
+    assert_eq!(v.pending_len(), 1);
+    v.commit().unwrap();
+    assert_eq!(v.pending_len(), 0);
+    assert_eq!(&v.committed()[..], [ROW1, ROW2, ROW3, ROW4]);
+
+    // Commit empty buffer
+    v.commit().unwrap();
+    assert_eq!(v.len(), 4);
+}
+
+#[test]
+fn test_default_batch_len() {
+    let v = BufferedMmapVec::<u64>::new();
+    assert_eq!(v.batch_len(), 512);
+
+    let v = BufferedMmapVec::<[u8; 5000]>::default();
+    assert_eq!(v.batch_len(), 1);
+}
+
+#[test]
+fn test_into_inner() {
+    let mut v = BufferedMmapVec::from(MmapVec::<u64>::try_from([1, 2]).unwrap());
+    for i in 3..10_000 {
+        v.push(i).unwrap();
+    }
+    assert!(v.pending_len() > 0);
+
+    let v = v.into_inner().unwrap();
+    assert_eq!(v.len(), 9_999);
+    assert!(v.iter().copied().eq(1..10_000));
+}
+
+#[test]
+fn test_geometric_growth() {
+    let mut v = BufferedMmapVec::<u64>::with_batch_len(10);
+    for i in 0..100_000 {
+        v.push(i).unwrap();
+    }
+    let v = v.into_inner().unwrap();
+    assert!(v.capacity() < 2 * 100_000 + 512);
+    assert!(v.iter().copied().eq(0..100_000));
+}
+
+#[test]
+fn test_commit_up_to_segment_size_limit() {
+    let inner = MmapVecBuilder::<u64>::new()
+        .capacity(0)
+        .segment_size_limit(1500 * 8)
+        .try_build()
+        .unwrap();
+    let mut v = BufferedMmapVec::from_vec_with_batch_len(inner, 100);
+
+    // Last commits cannot double capacity, but buffer still fits.
+    for i in 0..1500 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.pending_len(), 0);
+    assert_eq!(v.committed().capacity(), 1500);
+    assert!(v.committed().iter().copied().eq(0..1500));
+
+    // Vec is full.
+    for i in 0..99 {
+        v.push(i).unwrap();
+    }
+    assert!(v.push(99).is_err());
+    assert_eq!(v.pending_len(), 100);
+    assert_eq!(v.committed().len(), 1500);
+}
+
+#[test]
+fn test_drop() {
+    let counter = Arc::new(AtomicU32::new(0));
+
+    let mut v = BufferedMmapVec::<DroppableRow>::with_batch_len(4);
+    for _ in 0..6 {
+        v.push(DroppableRow::new(counter.clone())).unwrap();
+    }
+    let path = v.committed().path();
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+    // Pending values are committed then dropped with inner vec.
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 6);
+    assert!(!path.exists());
+
+    let mut v = BufferedMmapVec::<DroppableRow>::with_batch_len(4);
+    for _ in 0..6 {
+        v.push(DroppableRow::new(counter.clone())).unwrap();
+    }
+    let v = v.into_inner().unwrap();
+    assert_eq!(v.len(), 6);
+    assert_eq!(counter.load(Ordering::Relaxed), 6);
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 12);
+}
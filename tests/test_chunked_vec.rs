@@ -0,0 +1,144 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use mmap_vec::ChunkedMmapVec;
+
+pub use data_gen::*;
+
+mod data_gen;
+
+#[test]
+fn test_new() {
+    let v = ChunkedMmapVec::<DataRow>::new();
+    assert_eq!(v.len(), 0);
+    assert!(v.is_empty());
+    assert_eq!(v.capacity(), 0);
+    assert_eq!(v.chunk_count(), 0);
+    assert_eq!(v.chunk_capacity(), 43690);
+    assert_eq!(v.get(0), None);
+}
+
+#[test]
+#[should_panic = "Chunk capacity cannot be 0"]
+fn test_zero_chunk_capacity() {
+    let _ = ChunkedMmapVec::<DataRow>::with_chunk_capacity(0);
+}
+
+#[test]
+fn test_push_pop() {
+    let mut v = ChunkedMmapVec::<DataRow>::with_chunk_capacity(2);
+
+    v.push(ROW1).unwrap();
+    assert_eq!(v.chunk_count(), 1);
+    v.push(ROW2).unwrap();
+    assert_eq!(v.chunk_count(), 1);
+    v.push(ROW3).unwrap();
+    assert_eq!(v.chunk_count(), 2);
+    assert_eq!(v.capacity(), 4);
+    assert_eq!(v.disk_size(), 4 * 24);
+
+    assert_eq!(v.len(), 3);
+    assert_eq!(v[0], ROW1);
+    assert_eq!(v[1], ROW2);
+    assert_eq!(v[2], ROW3);
+    assert_eq!(v.get(3), None);
+
+    assert_eq!(v.pop(), Some(ROW3));
+    assert_eq!(v.pop(), Some(ROW2));
+
+    // Chunks are reused
+    v.push(ROW4).unwrap();
+    v.push(ROW4).unwrap();
+    assert_eq!(v.chunk_count(), 2);
+    assert!(v.iter().eq([ROW1, ROW4, ROW4].iter()));
+
+    assert_eq!(v.pop(), Some(ROW4));
+    assert_eq!(v.pop(), Some(ROW4));
+    assert_eq!(v.pop(), Some(ROW1));
+    assert_eq!(v.pop(), None);
+    assert!(v.is_empty());
+}
+
+#[test]
+fn test_assign() {
+    let mut v = ChunkedMmapVec::<DataRow>::with_chunk_capacity(2);
+    v.push(ROW1).unwrap();
+    v.push(ROW1).unwrap();
+    v.push(ROW1).unwrap();
+
+    v[2] = ROW2;
+    *v.get_mut(0).unwrap() = ROW3;
+    assert!(v.get_mut(3).is_none());
+    assert!(v.iter().eq([ROW3, ROW1, ROW2].iter()));
+}
+
+#[test]
+#[should_panic = "index out of bounds: the len is 1 but the index is 1"]
+fn test_index_out_of_bounds() {
+    let mut v = ChunkedMmapVec::<DataRow>::with_chunk_capacity(2);
+    v.push(ROW1).unwrap();
+    let _ = v[1];
+}
+
+#[test]
+fn test_chunks() {
+    let mut v = ChunkedMmapVec::<u32>::with_chunk_capacity(3);
+    assert_eq!(v.chunks().count(), 0);
+
+    for i in 0..7 {
+        v.push(i).unwrap();
+    }
+
+    let chunks: Vec<_> = v.chunks().collect();
+    assert_eq!(chunks, [&[0, 1, 2][..], &[3, 4, 5], &[6]]);
+
+    // Empty chunks are skipped
+    v.pop();
+    assert_eq!(v.chunks().count(), 2);
+    assert_eq!(v.chunk_count(), 3);
+}
+
+#[test]
+fn test_clear_and_drop() {
+    let mut v = ChunkedMmapVec::<DroppableRow>::with_chunk_capacity(4);
+    let counter = Arc::new(AtomicU32::new(0));
+
+    for _ in 0..10 {
+        v.push(DroppableRow::new(counter.clone())).unwrap();
+    }
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+    v.clear();
+    assert_eq!(v.len(), 0);
+    assert_eq!(v.chunk_count(), 3);
+    assert_eq!(counter.load(Ordering::Relaxed), 10);
+
+    for _ in 0..5 {
+        v.push(DroppableRow::new(counter.clone())).unwrap();
+    }
+    assert_eq!(v.chunk_count(), 3);
+
+    let paths = v.paths().to_vec();
+    assert_eq!(paths.len(), 3);
+    assert!(paths.iter().all(|path| path.exists()));
+
+    v.flush().unwrap();
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 15);
+    assert!(paths.iter().all(|path| !path.exists()));
+}
+
+#[test]
+fn test_many_chunks() {
+    let mut v = ChunkedMmapVec::<u64>::with_chunk_capacity(512);
+
+    for i in 0..100_000 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.len(), 100_000);
+    assert_eq!(v.chunk_count(), 196);
+    assert!(v.iter().copied().eq(0..100_000));
+    assert!((0..100_000).all(|i| v[i as usize] == i));
+}
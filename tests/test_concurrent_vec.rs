@@ -0,0 +1,105 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use mmap_vec::{ConcurrentMmapVec, MmapVec};
+
+pub use data_gen::*;
+
+mod data_gen;
+
+#[test]
+fn test_push_within_capacity() {
+    let v = ConcurrentMmapVec::<DataRow>::with_capacity(3).unwrap();
+    assert_eq!(v.len(), 0);
+    assert!(v.is_empty());
+    assert_eq!(&v[..], &[]);
+
+    assert_eq!(v.push_within_capacity(ROW1), Ok(()));
+    assert_eq!(v.push_within_capacity(ROW2), Ok(()));
+    assert_eq!(v.push_within_capacity(ROW3), Ok(()));
+    assert_eq!(v.push_within_capacity(ROW4), Err(ROW4));
+    assert_eq!(v.len(), 3);
+    assert_eq!(&v[..], &[ROW1, ROW2, ROW3]);
+}
+
+#[test]
+fn test_push_grow() {
+    let mut v = ConcurrentMmapVec::<u32>::new();
+    assert_eq!(v.capacity(), 0);
+    assert_eq!(v.push_within_capacity(8), Err(8));
+
+    v.push(8).unwrap();
+    assert_eq!(v.capacity(), 1024);
+    assert_eq!(v.push_within_capacity(9), Ok(()));
+
+    v.reserve(2000).unwrap();
+    assert_eq!(v.capacity(), 2048);
+    assert_eq!(&v[..], [8, 9]);
+
+    let inner = v.into_inner();
+    assert_eq!(&inner[..], [8, 9]);
+    assert_eq!(inner.capacity(), 2048);
+}
+
+#[test]
+fn test_concurrent_writer_and_readers() {
+    const READERS: usize = 3;
+    const COUNT: usize = 40_000;
+
+    let v = ConcurrentMmapVec::<usize>::with_capacity(COUNT).unwrap();
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 0..COUNT {
+                assert!(v.push_within_capacity(i).is_ok());
+            }
+        });
+
+        for _ in 0..READERS {
+            s.spawn(|| {
+                while v.len() < COUNT {
+                    // Every visible value must have been written, in order.
+                    let values = &v[..];
+                    assert!(values.iter().enumerate().all(|(i, x)| *x == i));
+                }
+            });
+        }
+    });
+
+    assert_eq!(v.push_within_capacity(0), Err(0));
+    assert!(v.iter().copied().eq(0..COUNT));
+}
+
+#[test]
+fn test_drop() {
+    let counter = Arc::new(AtomicU32::new(0));
+
+    let mut v = ConcurrentMmapVec::<DroppableRow>::with_capacity(2).unwrap();
+    assert!(v
+        .push_within_capacity(DroppableRow::new(counter.clone()))
+        .is_ok());
+    v.push(DroppableRow::new(counter.clone())).unwrap();
+    v.push(DroppableRow::new(counter.clone())).unwrap();
+    assert_eq!(v.len(), 3);
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn test_from_mmap_vec() {
+    let inner = MmapVec::<i32>::try_from([1, 2, 3]).unwrap();
+    let path = inner.path();
+
+    let v = ConcurrentMmapVec::from(inner);
+    assert_eq!(&v[..], [1, 2, 3]);
+
+    drop(v);
+    assert!(!path.exists());
+}
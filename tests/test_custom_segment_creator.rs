@@ -1,5 +1,5 @@
 use glob::glob;
-use mmap_vec::{DefaultSegmentBuilder, MmapVecBuilder};
+use mmap_vec::{DefaultSegmentBuilder, MmapVec, MmapVecBuilder};
 
 fn get_seg_count() -> usize {
     let mut count = 0;
@@ -39,3 +39,23 @@ fn test_custom_segment_builder() {
     drop(v);
     assert_eq!(get_seg_count(), start_file_count);
 }
+
+#[test]
+fn test_with_builder() {
+    let builder = DefaultSegmentBuilder::with_path("/tmp/test_with_builder");
+    builder.create_dir_all().unwrap();
+
+    // No file is created until first push.
+    let mut v = MmapVec::<u32, _>::with_builder(builder);
+    assert_eq!(v.capacity(), 0);
+    assert!(v.path().starts_with("/tmp/test_with_builder"));
+    assert!(!v.path().exists());
+
+    v.push(42).unwrap();
+    assert!(v.path().exists());
+    assert_eq!(&v[..], [42]);
+
+    let path = v.path();
+    drop(v);
+    assert!(!path.exists());
+}
@@ -1,5 +1,5 @@
 use std::{
-    fs,
+    fs, io,
     path::PathBuf,
     sync::{
         atomic::{AtomicU32, Ordering},
@@ -7,7 +7,7 @@ use std::{
     },
 };
 
-use mmap_vec::Segment;
+use mmap_vec::{MmapVecError, Segment};
 
 pub use data_gen::*;
 pub use temporary_seg::*;
@@ -19,7 +19,8 @@ fn assert_empty(mut segment: Segment<DataRow>) {
     assert_eq!(segment.len(), 0);
     assert_eq!(segment.capacity(), 0);
     assert_eq!(segment.disk_size(), 0);
-    // assert_eq!(&segment[..], &[]); // Check why this does not work with null slice.
+    assert_eq!(&segment[..], &[]);
+    assert_eq!(&mut segment[..], &mut []);
 
     // Check we cannot add / pop anything
     assert_eq!(segment.push_within_capacity(ROW1), Err(ROW1));
@@ -210,13 +211,13 @@ fn test_truncate_first() {
         let mut segment = TemporarySegment::<u8, _>::open_rw("test_truncate_first.seg", 5).unwrap();
         assert_eq!(&segment[..], []);
 
-        segment.truncate_first(0);
+        assert_eq!(segment.truncate_first(0), 0);
         assert_eq!(&segment[..], []);
 
-        segment.truncate_first(3);
+        assert_eq!(segment.truncate_first(3), 0);
         assert_eq!(&segment[..], []);
 
-        segment.truncate_first(10);
+        assert_eq!(segment.truncate_first(10), 0);
         assert_eq!(&segment[..], []);
     }
 
@@ -233,35 +234,35 @@ fn test_truncate_first() {
     // Truncate 0 on with data segment
     {
         let mut segment = build_test_seg();
-        segment.truncate_first(0);
+        assert_eq!(segment.truncate_first(0), 0);
         assert_eq!(&segment[..], [1, 2, 6, 4]);
     }
 
     // Truncate half on with data segment
     {
         let mut segment = build_test_seg();
-        segment.truncate_first(2);
+        assert_eq!(segment.truncate_first(2), 2);
         assert_eq!(&segment[..], [6, 4]);
     }
 
     // Truncate almost everything on with data segment
     {
         let mut segment = build_test_seg();
-        segment.truncate_first(3);
+        assert_eq!(segment.truncate_first(3), 3);
         assert_eq!(&segment[..], [4]);
     }
 
     // Truncate everything on with data segment
     {
         let mut segment = build_test_seg();
-        segment.truncate_first(4);
+        assert_eq!(segment.truncate_first(4), 4);
         assert_eq!(&segment[..], []);
     }
 
     // Truncate above capacity on segment with data
     {
         let mut segment = build_test_seg();
-        segment.truncate_first(500);
+        assert_eq!(segment.truncate_first(500), 4);
         assert_eq!(&segment[..], []);
     }
 }
@@ -380,6 +381,8 @@ fn test_advice_prefetch() {
         segment.advice_prefetch_all_pages();
         segment.advice_prefetch_page_at(0);
         segment.advice_prefetch_page_at(42);
+        segment.advice_prefetch_strided(0, 10, 5);
+        segment.advice_prefetch_range(0..10);
     }
 
     // Test prefetch wih no data
@@ -389,6 +392,7 @@ fn test_advice_prefetch() {
         segment.advice_prefetch_page_at(0);
         segment.advice_prefetch_page_at(18);
         segment.advice_prefetch_page_at(25);
+        segment.advice_prefetch_strided(0, 3, 10);
     }
 
     // Test prefetch with data
@@ -403,6 +407,13 @@ fn test_advice_prefetch() {
         segment.advice_prefetch_page_at(0);
         segment.advice_prefetch_page_at(18);
         segment.advice_prefetch_page_at(25);
+        segment.advice_prefetch_strided(0, 1, 4);
+        segment.advice_prefetch_strided(1, 2, 100);
+        segment.advice_prefetch_strided(3, 0, 100);
+        segment.advice_prefetch_strided(usize::MAX, usize::MAX, 3);
+        segment.advice_prefetch_range(0..4);
+        segment.advice_prefetch_range(2..100);
+        segment.advice_prefetch_range(10..20);
     }
 }
 
@@ -411,6 +422,204 @@ fn test_debug() {
     let s = Segment::<u8>::null();
     assert_eq!(
         format!("{s:?}"),
-        "Segment { addr: 0x0, len: 0, capacity: 0 }"
+        "Segment { addr: 0x0, len: 0, capacity: 0, guard_size: 0, addr_range: None }"
+    );
+}
+
+#[test]
+fn test_addr_range() {
+    let s = Segment::<u8>::null();
+    assert_eq!(s.addr_range(), None);
+
+    let s = TemporarySegment::<u64, _>::open_rw("test_addr_range.seg", 20).unwrap();
+    let (start, end) = s.addr_range().unwrap();
+    assert_eq!(start, s.as_ptr() as usize);
+    assert_eq!(end - start, 20 * 8);
+    assert!(format!("{s:?}").contains(&format!("addr_range: Some(({start}, {end}))")));
+}
+
+#[test]
+fn test_dedup_by() {
+    let mut segment = TemporarySegment::<u8, _>::open_rw("test_dedup_by.seg", 10).unwrap();
+    segment.dedup_by(|a, b| a == b);
+    assert_eq!(&segment[..], []);
+
+    for x in [1, 1, 2, 3, 3, 3, 1, 4] {
+        segment.push_within_capacity(x).unwrap();
+    }
+
+    segment.dedup_by(|a, b| a == b);
+    assert_eq!(&segment[..], [1, 2, 3, 1, 4]);
+}
+
+#[test]
+fn test_insert_within_capacity() {
+    let mut segment = Segment::<u8>::null();
+    assert_eq!(segment.insert_within_capacity(0, 1), Err(1));
+
+    let mut segment =
+        TemporarySegment::<u8, _>::open_rw("test_insert_within_capacity.seg", 4).unwrap();
+    assert_eq!(segment.insert_within_capacity(0, 2), Ok(()));
+    assert_eq!(segment.insert_within_capacity(0, 1), Ok(()));
+    assert_eq!(segment.insert_within_capacity(2, 4), Ok(()));
+    assert_eq!(segment.insert_within_capacity(2, 3), Ok(()));
+    assert_eq!(&segment[..], [1, 2, 3, 4]);
+
+    // Segment is full
+    assert_eq!(segment.insert_within_capacity(1, 5), Err(5));
+    assert_eq!(&segment[..], [1, 2, 3, 4]);
+}
+
+#[test]
+#[should_panic = "insertion index (is 2) should be <= len (is 1)"]
+fn test_insert_within_capacity_out_of_bounds() {
+    let mut segment =
+        TemporarySegment::<u8, _>::open_rw("test_insert_within_capacity_oob.seg", 4).unwrap();
+    segment.push_within_capacity(1).unwrap();
+    let _ = segment.insert_within_capacity(2, 1);
+}
+
+#[test]
+fn test_remap_to() {
+    let counter = Arc::new(AtomicU32::new(0));
+    let p1 = PathBuf::from("test_remap_to_src.seg");
+    let p2 = PathBuf::from("test_remap_to_dst.seg");
+
+    // Test with null
+    let mut segment = Segment::<DroppableRow>::null();
+    segment.remap_to(&p2).unwrap();
+    assert_eq!(segment.capacity(), 0);
+
+    // Test with data
+    let mut segment = Segment::<DroppableRow>::open_rw(&p1, 5).unwrap();
+    for _ in 0..3 {
+        assert!(segment
+            .push_within_capacity(DroppableRow::new(counter.clone()))
+            .is_ok());
+    }
+    let addr = segment.as_ptr();
+
+    segment.remap_to(&p2).unwrap();
+    assert_ne!(segment.as_ptr(), addr);
+    assert_eq!(segment.len(), 3);
+    assert_eq!(segment.capacity(), 5);
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+    assert_eq!(fs::metadata(&p2).unwrap().len(), segment.disk_size() as u64);
+
+    // Old file is left untouched
+    assert!(p1.exists());
+
+    // New mapping is usable
+    assert!(segment
+        .push_within_capacity(DroppableRow::new(counter.clone()))
+        .is_ok());
+    drop(segment);
+    assert_eq!(counter.load(Ordering::Relaxed), 4);
+
+    let _ = fs::remove_file(&p1);
+    let _ = fs::remove_file(&p2);
+}
+
+#[test]
+fn test_advice_free() {
+    // Test free with null
+    {
+        let segment = Segment::<i32>::null();
+        segment.advice_free_range(0..42);
+    }
+
+    // Test free with data
+    {
+        let mut segment =
+            TemporarySegment::<u64, _>::open_rw("test_advice_free.seg", 2000).unwrap();
+        for i in 0..2000 {
+            assert!(segment.push_within_capacity(i).is_ok());
+        }
+
+        segment.advice_free_range(0..10);
+        segment.advice_free_range(10..1500);
+        segment.advice_free_range(1500..5000);
+        segment.advice_free_range(20..20);
+        segment.advice_free_range(0..segment.capacity());
+
+        // Data are still there since segment is backed by a file.
+        assert!(segment.iter().enumerate().all(|(i, x)| *x == i as u64));
+    }
+}
+
+#[test]
+fn test_resident_pages() {
+    // Test with null
+    let segment = Segment::<i32>::null();
+    assert_eq!(segment.resident_pages().unwrap(), 0);
+
+    // Test with data: 2000 * 8 bytes fit in 4 pages
+    let mut segment = TemporarySegment::<u64, _>::open_rw("test_resident_pages.seg", 2000).unwrap();
+    assert!(segment.resident_pages().unwrap() <= 4);
+
+    for i in 0..2000 {
+        assert!(segment.push_within_capacity(i).is_ok());
+    }
+    assert_eq!(segment.resident_pages().unwrap(), 4);
+}
+
+#[test]
+fn test_validate_file() {
+    let p = PathBuf::from("test_validate_file.seg");
+
+    // Missing file
+    let _ = fs::remove_file(&p);
+    assert!(matches!(
+        Segment::<u64>::validate_file(&p),
+        Err(MmapVecError::FileIo { .. })
+    ));
+
+    // Valid files
+    fs::write(&p, []).unwrap();
+    assert_eq!(Segment::<u64>::validate_file(&p).unwrap(), 0);
+
+    fs::write(&p, [0; 48]).unwrap();
+    assert_eq!(Segment::<u64>::validate_file(&p).unwrap(), 6);
+    assert_eq!(Segment::<DataRow>::validate_file(&p).unwrap(), 2);
+    assert_eq!(Segment::<u8>::validate_file(&p).unwrap(), 48);
+
+    // Mismatched file size
+    fs::write(&p, [0; 50]).unwrap();
+    let err = Segment::<u64>::validate_file(&p).unwrap_err();
+    assert!(matches!(
+        err,
+        MmapVecError::InvalidFileSize {
+            file_size: 50,
+            element_size: 8
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "invalid file size: 50 is not a multiple of element size 8"
     );
+    assert!(Segment::<DataRow>::validate_file(&p).is_err());
+
+    let _ = fs::remove_file(&p);
+}
+
+#[test]
+fn test_open_error_path() {
+    let p = PathBuf::from("/this/dir/does/not/exist/test_open_error_path.seg");
+    let err = Segment::<u64>::open_rw(&p, 10).unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err
+        .to_string()
+        .starts_with("io error on /this/dir/does/not/exist/test_open_error_path.seg: "));
+
+    let inner = err.get_ref().unwrap().downcast_ref::<MmapVecError>();
+    assert!(matches!(inner, Some(MmapVecError::FileIo { path, .. }) if *path == p));
+}
+
+#[test]
+fn test_open_capacity_overflow() {
+    let p = PathBuf::from("test_open_capacity_overflow.seg");
+    let err = Segment::<u64>::open_rw(&p, usize::MAX / 4).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(!p.exists());
 }
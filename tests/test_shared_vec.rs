@@ -0,0 +1,109 @@
+use std::thread;
+
+use mmap_vec::MmapVec;
+
+#[test]
+fn test_share_between_threads() {
+    let mut v = MmapVec::<u64>::new();
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    let path = v.path();
+
+    let shared = v.into_shared().unwrap();
+    assert_eq!(shared.share_count(), 1);
+    assert_eq!(shared.path(), path);
+    assert_eq!(shared.len(), 10_000);
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            thread::spawn(move || shared.iter().sum::<u64>())
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), (0..10_000).sum());
+    }
+    assert_eq!(shared.share_count(), 1);
+
+    // Backing file is removed with last clone
+    let other = shared.clone();
+    assert_eq!(shared.share_count(), 2);
+    drop(shared);
+    assert!(path.exists());
+    drop(other);
+    assert!(!path.exists());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_read_only_mapping() {
+    /// Get permissions of mapping starting at `addr`.
+    fn mapping_perms(addr: usize) -> Option<String> {
+        let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+        maps.lines().find_map(|line| {
+            let (range, rest) = line.split_once(' ')?;
+            let (start, _) = range.split_once('-')?;
+            (usize::from_str_radix(start, 16).ok()? == addr).then(|| rest[..4].to_string())
+        })
+    }
+
+    let v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    let shared = v.into_shared().unwrap();
+    assert_eq!(
+        mapping_perms(shared.as_ptr() as usize).as_deref(),
+        Some("r--s")
+    );
+
+    let v = shared.into_mut().unwrap();
+    assert_eq!(mapping_perms(v.as_ptr() as usize).as_deref(), Some("rw-s"));
+}
+
+#[test]
+fn test_make_mut() {
+    let v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    let shared = v.into_shared().unwrap();
+
+    let mut v = shared.make_mut().unwrap();
+    assert_ne!(v.path(), shared.path());
+    v.push(4).unwrap();
+    v[0] = 10;
+    assert_eq!(&v[..], [10, 2, 3, 4]);
+    assert_eq!(&shared[..], [1, 2, 3]);
+    assert_eq!(shared.share_count(), 1);
+}
+
+#[test]
+fn test_into_mut() {
+    let v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    let path = v.path();
+    let shared = v.into_shared().unwrap();
+
+    // Other clones alive: content is copied
+    let other = shared.clone();
+    let mut v = other.into_mut().unwrap();
+    assert_ne!(v.path(), path);
+    v.push(4).unwrap();
+    assert_eq!(&v[..], [1, 2, 3, 4]);
+    assert_eq!(&shared[..], [1, 2, 3]);
+    assert_eq!(shared.share_count(), 1);
+
+    // Last clone: segment is reused
+    let mut v = shared.into_mut().unwrap();
+    assert_eq!(v.path(), path);
+    v[0] = 10;
+    v.push(4).unwrap();
+    assert_eq!(&v[..], [10, 2, 3, 4]);
+}
+
+#[test]
+fn test_empty() {
+    let v = MmapVec::<u64>::new();
+    let shared = v.into_shared().unwrap();
+    assert!(shared.is_empty());
+    assert_eq!(&shared.clone()[..], &[] as &[u64]);
+
+    let mut v = shared.into_mut().unwrap();
+    v.push(1).unwrap();
+    assert_eq!(&v[..], [1]);
+}
@@ -1,6 +1,7 @@
 use mmap_vec::{MmapStats, MmapVec};
 
 #[test]
+#[allow(clippy::default_constructed_unit_structs)]
 fn test_stats() {
     let stats = MmapStats::default();
 
@@ -8,28 +9,42 @@ fn test_stats() {
     assert_eq!(stats.ftruncate_failed(), 0);
     assert_eq!(stats.map_failed(), 0);
     assert_eq!(stats.unmap_failed(), 0);
+    assert_eq!(stats.vec_grows(), 0);
     assert_eq!(
         format!("{stats:?}"),
-        "MmapStats { active: 0, ftruncate_failed: 0, map_failed: 0, unmap_failed: 0 }"
+        "MmapStats { active: 0, ftruncate_failed: 0, map_failed: 0, unmap_failed: 0, vec_grows: 0 }"
     );
 
-    let v = MmapVec::<u8>::with_capacity(500).unwrap();
+    let mut v = MmapVec::<u8>::with_capacity(500).unwrap();
     assert_eq!(stats.active_segment(), 1);
     assert_eq!(stats.ftruncate_failed(), 0);
     assert_eq!(stats.map_failed(), 0);
     assert_eq!(stats.unmap_failed(), 0);
+    assert_eq!(stats.vec_grows(), 0);
     assert_eq!(
         format!("{stats:?}"),
-        "MmapStats { active: 1, ftruncate_failed: 0, map_failed: 0, unmap_failed: 0 }"
+        "MmapStats { active: 1, ftruncate_failed: 0, map_failed: 0, unmap_failed: 0, vec_grows: 0 }"
     );
 
+    // No-op reserve is not counted
+    v.reserve(100).unwrap();
+    assert_eq!(stats.vec_grows(), 0);
+
+    v.reserve(1000).unwrap();
+    assert_eq!(stats.active_segment(), 1);
+    assert_eq!(stats.vec_grows(), 1);
+
+    v.reserve_exact(5000).unwrap();
+    assert_eq!(stats.vec_grows(), 2);
+
     drop(v);
     assert_eq!(stats.active_segment(), 0);
     assert_eq!(stats.ftruncate_failed(), 0);
     assert_eq!(stats.map_failed(), 0);
     assert_eq!(stats.unmap_failed(), 0);
+    assert_eq!(stats.vec_grows(), 2);
     assert_eq!(
         format!("{stats:?}"),
-        "MmapStats { active: 0, ftruncate_failed: 0, map_failed: 0, unmap_failed: 0 }"
+        "MmapStats { active: 0, ftruncate_failed: 0, map_failed: 0, unmap_failed: 0, vec_grows: 2 }"
     );
 }
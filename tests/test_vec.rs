@@ -1,9 +1,16 @@
-use std::sync::{
-    atomic::{AtomicU32, Ordering},
-    Arc,
+use std::{
+    io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
 };
 
-use mmap_vec::{DefaultSegmentBuilder, MmapVec};
+use mmap_vec::{
+    AccessPattern, DefaultSegmentBuilder, MmapVec, MmapVecBuilder, MmapVecError, Segment,
+    SegmentBuilder,
+};
 
 pub use data_gen::*;
 
@@ -47,6 +54,107 @@ fn test_with_capacity() {
     assert_eq!(v.capacity(), 500);
 }
 
+#[test]
+fn test_push_and_index() {
+    let mut v = MmapVec::<DataRow>::new();
+    assert_eq!(v.push_and_index(ROW1).unwrap(), 0);
+    assert_eq!(v.push_and_index(ROW2).unwrap(), 1);
+
+    // Index is still valid after growth
+    while v.len() < v.capacity() {
+        v.push(ROW3).unwrap();
+    }
+    let capacity = v.capacity();
+    assert_eq!(v.push_and_index(ROW4).unwrap(), capacity);
+    assert_eq!(v[capacity], ROW4);
+}
+
+#[test]
+fn test_extend_within_capacity() {
+    // Test on null segment
+    let mut v = MmapVec::<DataRow>::new();
+    assert_eq!(v.extend_within_capacity([]), Ok(()));
+    assert_eq!(v.extend_within_capacity([ROW1, ROW2]), Err(ROW1));
+    assert_eq!(v.capacity(), 0);
+
+    // Test fill
+    let mut v = MmapVec::<DataRow>::with_capacity(3).unwrap();
+    assert_eq!(v.extend_within_capacity([ROW1, ROW2]), Ok(()));
+    assert_eq!(&v[..], [ROW1, ROW2]);
+
+    let mut iter = [ROW3, ROW4, ROW1].into_iter();
+    assert_eq!(v.extend_within_capacity(iter.by_ref()), Err(ROW4));
+    assert_eq!(&v[..], [ROW1, ROW2, ROW3]);
+    assert_eq!(v.capacity(), 3);
+
+    // Remaining values are untouched
+    assert_eq!(iter.next(), Some(ROW1));
+    assert_eq!(iter.next(), None);
+}
+
+#[test]
+fn test_insert() {
+    let mut v = MmapVec::<DroppableRow>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Insert in null segment
+    v.insert(0, DroppableRow::new(counter.clone())).unwrap();
+    assert_eq!(v.len(), 1);
+
+    let mut v = MmapVec::<u32>::with_capacity(3).unwrap();
+    v.insert(0, 3).unwrap();
+    v.insert(0, 1).unwrap();
+    v.insert(1, 2).unwrap();
+    assert_eq!(v.capacity(), 3);
+
+    // Insert trigger growth
+    v.insert(3, 4).unwrap();
+    v.insert(0, 0).unwrap();
+    assert_eq!(&v[..], [0, 1, 2, 3, 4]);
+    assert_eq!(v.capacity(), 2048);
+}
+
+#[test]
+#[should_panic = "insertion index (is 4) should be <= len (is 3)"]
+fn test_insert_out_of_bounds() {
+    let mut v = MmapVec::<u32>::try_from([1, 2, 3]).unwrap();
+    let _ = v.insert(4, 1);
+}
+
+#[test]
+fn test_insert_sorted() {
+    let mut v = MmapVec::<(u32, char)>::new();
+    assert_eq!(v.insert_sorted((5, 'a')).unwrap(), 0);
+    assert_eq!(v.insert_sorted((2, 'a')).unwrap(), 0);
+    assert_eq!(v.insert_sorted((9, 'a')).unwrap(), 2);
+    assert_eq!(v.insert_sorted((5, 'b')).unwrap(), 2);
+    assert_eq!(v.insert_sorted((5, 'a')).unwrap(), 2);
+    assert_eq!(&v[..], [(2, 'a'), (5, 'a'), (5, 'a'), (5, 'b'), (9, 'a')]);
+
+    // Many inserts
+    let mut v = MmapVec::<u32>::new();
+    for i in 0..2000u32 {
+        v.insert_sorted(i.wrapping_mul(7919) % 2003).unwrap();
+    }
+    assert_eq!(v.len(), 2000);
+    assert!(v.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[test]
+fn test_element_layout() {
+    let v = MmapVec::<DataRow>::new();
+    assert_eq!(v.element_size(), 24);
+    assert_eq!(v.element_align(), 8);
+
+    let v = MmapVec::<u16>::new();
+    assert_eq!(v.element_size(), 2);
+    assert_eq!(v.element_align(), 2);
+
+    let v = MmapVec::<[u8; 3]>::new();
+    assert_eq!(v.element_size(), 3);
+    assert_eq!(v.element_align(), 1);
+}
+
 #[test]
 fn test_assign() {
     let mut v = MmapVec::<DataRow>::new();
@@ -136,28 +244,28 @@ fn test_truncate_first() {
     // Truncate 0
     {
         let mut v = build_vec();
-        v.truncate_first(0);
+        assert_eq!(v.truncate_first(0), 0);
         assert_eq!(&v[..], [8, 5, 3, 12]);
     }
 
     // Truncate half
     {
         let mut v = build_vec();
-        v.truncate_first(2);
+        assert_eq!(v.truncate_first(2), 2);
         assert_eq!(&v[..], [3, 12]);
     }
 
     // Truncate len
     {
         let mut v = build_vec();
-        v.truncate_first(v.len());
+        assert_eq!(v.truncate_first(v.len()), 4);
         assert_eq!(&v[..], []);
     }
 
     // Truncate too much
     {
         let mut v = build_vec();
-        v.truncate_first(v.len() + 1000);
+        assert_eq!(v.truncate_first(v.len() + 1000), 4);
         assert_eq!(&v[..], []);
     }
 }
@@ -207,6 +315,28 @@ fn test_equals() {
     assert_ne!(s1, s2);
 }
 
+#[test]
+fn test_equals_large() {
+    let mut s1 = MmapVec::<u64>::new();
+    for i in 0..10_000 {
+        s1.push(i).unwrap();
+    }
+
+    // Different len
+    let mut s3 = s1.try_clone().unwrap();
+    s3.pop();
+    assert_ne!(s1, s3);
+    assert_ne!(s3, s1);
+
+    // Same len, different data
+    s3.push(0).unwrap();
+    assert_ne!(s1, s3);
+
+    let last = s3.len() - 1;
+    s3[last] = 9_999;
+    assert_eq!(s1, s3);
+}
+
 #[test]
 fn test_try_clone_null() {
     let mut s1 = MmapVec::<i32>::default();
@@ -393,6 +523,40 @@ fn test_try_from_vec() {
     assert_eq!(&vec[..], [8, 6, 4, -48, 16]);
 }
 
+#[test]
+fn test_try_from_vec_moves_values() {
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Empty vec
+    let vec = MmapVec::<DroppableRow>::try_from(Vec::new()).unwrap();
+    assert_eq!(vec.capacity(), 0);
+
+    // Values are moved, not dropped
+    let values: Vec<_> = (0..1000)
+        .map(|_| DroppableRow::new(counter.clone()))
+        .collect();
+    let vec = MmapVec::<DroppableRow>::try_from(values).unwrap();
+    assert_eq!(vec.len(), 1000);
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+    drop(vec);
+    assert_eq!(counter.load(Ordering::Relaxed), 1000);
+
+    // Multi MB byte vec
+    let bytes: Vec<u8> = (0..5_000_000).map(|x| x as u8).collect();
+    let vec = MmapVec::<u8>::try_from(bytes.clone()).unwrap();
+    assert_eq!(&vec[..], bytes);
+}
+
+#[test]
+fn test_try_from_string() {
+    let vec = MmapVec::<u8>::try_from(String::from("hello world")).unwrap();
+    assert_eq!(&vec[..], b"hello world");
+
+    let vec = MmapVec::<u8>::try_from(String::new()).unwrap();
+    assert!(vec.is_empty());
+}
+
 #[test]
 #[should_panic = "Zero sized type are not supported"]
 fn test_zero_sized_type() {
@@ -400,3 +564,1768 @@ fn test_zero_sized_type() {
 
     let _vec = MmapVec::<VoidStruct>::with_capacity(50).unwrap();
 }
+
+#[test]
+fn test_dedup_by() {
+    let mut v = MmapVec::<(u32, DroppableRow)>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    for key in [1, 1, 2, 2, 2, 3] {
+        assert!(v.push((key, DroppableRow::new(counter.clone()))).is_ok());
+    }
+    assert_eq!(v.len(), 6);
+
+    // Check duplicates are drop
+    v.dedup_by(|a, b| a.0 == b.0);
+    assert_eq!(v.len(), 3);
+    assert_eq!(v.iter().map(|x| x.0).collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(counter.load(Ordering::Relaxed), 3);
+
+    // Check nothing else is removed
+    v.dedup_by(|a, b| a.0 == b.0);
+    assert_eq!(v.len(), 3);
+    assert_eq!(counter.load(Ordering::Relaxed), 3);
+
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 6);
+}
+
+#[test]
+fn test_in_place_scans_on_cold_pages() {
+    let mut v = MmapVec::<u64>::new();
+    for i in 0..300_000 {
+        v.push(i / 3).unwrap();
+    }
+
+    // Scans spanning many prefetch blocks, pages being evicted first
+    v.advise(AccessPattern::DontNeed);
+    v.dedup_by(|a, b| a == b);
+    assert!(v.iter().copied().eq(0..100_000));
+
+    v.advise(AccessPattern::DontNeed);
+    v.retain(|x| x % 2 == 0);
+    assert!(v.iter().copied().eq((0..100_000).step_by(2)));
+}
+
+#[test]
+fn test_dedup_with_counts() {
+    struct Keyed {
+        key: u32,
+        _row: DroppableRow,
+    }
+
+    impl PartialEq for Keyed {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    let mut v = MmapVec::<Keyed>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Empty vec
+    assert_eq!(v.dedup_with_counts(), []);
+
+    // Single element
+    v.push(Keyed {
+        key: 7,
+        _row: DroppableRow::new(counter.clone()),
+    })
+    .unwrap();
+    assert_eq!(v.dedup_with_counts(), [(0, 1)]);
+    v.clear();
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+    for key in [1, 1, 2, 3, 3, 3, 3, 1] {
+        v.push(Keyed {
+            key,
+            _row: DroppableRow::new(counter.clone()),
+        })
+        .unwrap();
+    }
+
+    // Check runs and duplicates drop
+    assert_eq!(v.dedup_with_counts(), [(0, 2), (1, 1), (2, 4), (3, 1)]);
+    assert_eq!(v.iter().map(|x| x.key).collect::<Vec<_>>(), [1, 2, 3, 1]);
+    assert_eq!(counter.load(Ordering::Relaxed), 5);
+
+    // Nothing else is removed
+    assert_eq!(v.dedup_with_counts(), [(0, 1), (1, 1), (2, 1), (3, 1)]);
+    assert_eq!(counter.load(Ordering::Relaxed), 5);
+
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 9);
+}
+
+#[test]
+fn test_retain() {
+    let mut v = MmapVec::<(u32, DroppableRow)>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Trigger on null segment
+    v.retain(|_| false);
+    assert_eq!(v.len(), 0);
+
+    for key in 0..10 {
+        assert!(v.push((key, DroppableRow::new(counter.clone()))).is_ok());
+    }
+    let capacity = v.capacity();
+
+    // Check removed values are drop and order is preserved
+    v.retain(|x| x.0 % 3 == 0);
+    assert_eq!(v.iter().map(|x| x.0).collect::<Vec<_>>(), [0, 3, 6, 9]);
+    assert_eq!(counter.load(Ordering::Relaxed), 6);
+    assert_eq!(v.capacity(), capacity);
+
+    v.retain(|_| true);
+    assert_eq!(v.len(), 4);
+    assert_eq!(counter.load(Ordering::Relaxed), 6);
+
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 10);
+}
+
+#[test]
+fn test_retain_keeps_order() {
+    let mut v = MmapVec::<u64>::new();
+    for i in (0..100_000).rev() {
+        v.push(i).unwrap();
+    }
+
+    // Predicate sees elements in order
+    let mut visited = Vec::new();
+    v.retain(|x| {
+        visited.push(*x);
+        x % 7 == 3
+    });
+    assert!(visited.into_iter().eq((0..100_000).rev()));
+
+    // Kept elements keep their relative order
+    assert!(v
+        .iter()
+        .copied()
+        .eq((0..100_000).rev().filter(|x| x % 7 == 3)));
+    assert!(v.windows(2).all(|w| w[0] > w[1]));
+}
+
+#[test]
+fn test_extract_if() {
+    let mut v = MmapVec::<u64>::new();
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    let capacity = v.capacity();
+
+    // Predicate can mutate kept elements
+    let extracted: Vec<_> = v
+        .extract_if(|x| {
+            *x *= 2;
+            *x % 3 == 0
+        })
+        .collect();
+    assert!(extracted
+        .into_iter()
+        .eq((0..10_000).map(|x| x * 2).filter(|x| x % 3 == 0)));
+    assert!(v
+        .iter()
+        .copied()
+        .eq((0..10_000).map(|x| x * 2).filter(|x| x % 3 != 0)));
+    assert_eq!(v.capacity(), capacity);
+
+    // Empty vec
+    let mut v = MmapVec::<u64>::new();
+    assert_eq!(v.extract_if(|_| true).count(), 0);
+    assert!(v.is_empty());
+}
+
+#[test]
+fn test_extract_if_partial() {
+    let mut v = MmapVec::<u8>::try_from([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+    // Not visited elements are kept
+    let mut iter = v.extract_if(|x| *x % 2 == 0);
+    assert_eq!(iter.next(), Some(2));
+    assert_eq!(iter.next(), Some(4));
+    drop(iter);
+    assert_eq!(&v[..], [1, 3, 5, 6, 7, 8]);
+
+    // Leaked iterator leaves vec empty
+    std::mem::forget(v.extract_if(|_| true));
+    assert!(v.is_empty());
+    v.push(9).unwrap();
+    assert_eq!(&v[..], [9]);
+}
+
+#[test]
+fn test_extract_if_drop() {
+    let counter = Arc::new(AtomicU32::new(0));
+    let mut v = MmapVec::<(u32, DroppableRow)>::new();
+    for i in 0..10 {
+        v.push((i, DroppableRow::new(counter.clone()))).unwrap();
+    }
+
+    // Extracted elements are moved out, not drop
+    let extracted: Vec<_> = v.extract_if(|(i, _)| *i >= 5).collect();
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+    assert_eq!(extracted.len(), 5);
+    assert_eq!(
+        v.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        [0, 1, 2, 3, 4]
+    );
+
+    drop(extracted);
+    assert_eq!(counter.load(Ordering::Relaxed), 5);
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 10);
+}
+
+#[test]
+fn test_retain_indices() {
+    let mut v = MmapVec::<(u32, DroppableRow)>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Trigger on null segment
+    v.retain_indices(&[]);
+    assert_eq!(v.len(), 0);
+
+    for key in 0..10 {
+        assert!(v.push((key, DroppableRow::new(counter.clone()))).is_ok());
+    }
+
+    // Check removed values are drop and order is preserved
+    v.retain_indices(&[0, 2, 3, 9]);
+    assert_eq!(v.iter().map(|x| x.0).collect::<Vec<_>>(), [0, 2, 3, 9]);
+    assert_eq!(counter.load(Ordering::Relaxed), 6);
+
+    v.retain_indices(&[0, 1, 2, 3]);
+    assert_eq!(v.len(), 4);
+    assert_eq!(counter.load(Ordering::Relaxed), 6);
+
+    v.retain_indices(&[]);
+    assert_eq!(v.len(), 0);
+    assert_eq!(counter.load(Ordering::Relaxed), 10);
+}
+
+#[test]
+#[should_panic = "index out of bounds: the len is 3 but the index is 3"]
+fn test_retain_indices_out_of_bounds() {
+    let mut v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    v.retain_indices(&[0, 3]);
+}
+
+#[test]
+#[should_panic = "Indexes to keep must be sorted and deduplicated"]
+fn test_retain_indices_unsorted() {
+    let mut v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    v.retain_indices(&[1, 0]);
+}
+
+#[test]
+#[should_panic = "Indexes to keep must be sorted and deduplicated"]
+fn test_retain_indices_duplicated() {
+    let mut v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    v.retain_indices(&[1, 1]);
+}
+
+#[test]
+fn test_retain_and_shrink() {
+    let mut v = MmapVec::<(u32, DroppableRow)>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Trigger on null segment
+    v.retain_and_shrink(|_| true).unwrap();
+    assert_eq!(v.capacity(), 0);
+
+    for key in 0..1000 {
+        assert!(v.push((key, DroppableRow::new(counter.clone()))).is_ok());
+    }
+    let disk_size = v.disk_size();
+    assert_eq!(v.capacity(), 1024);
+
+    // Check file is shrunk
+    v.retain_and_shrink(|x| x.0 < 200).unwrap();
+    assert_eq!(v.len(), 200);
+    assert_eq!(counter.load(Ordering::Relaxed), 800);
+    assert_eq!(v.capacity(), 256);
+    assert!(v.disk_size() < disk_size);
+    assert_eq!(
+        std::fs::metadata(v.path()).unwrap().len(),
+        v.disk_size() as u64
+    );
+    assert!(v.iter().enumerate().all(|(i, x)| x.0 == i as u32));
+
+    // Nothing to shrink
+    v.shrink_to_fit().unwrap();
+    assert_eq!(v.capacity(), 256);
+
+    // Vec can grow again
+    v.push((1000, DroppableRow::new(counter.clone()))).unwrap();
+    assert_eq!(v.len(), 201);
+
+    // Empty vec removes its file
+    v.retain_and_shrink(|_| false).unwrap();
+    assert_eq!(counter.load(Ordering::Relaxed), 1001);
+    assert_eq!(v.capacity(), 0);
+    assert!(!v.path().exists());
+
+    v.push((0, DroppableRow::new(counter.clone()))).unwrap();
+    assert!(v.path().exists());
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 1002);
+}
+
+#[test]
+fn test_shrink_to_fit_empty() {
+    // Null segment
+    let mut v = MmapVec::<u64>::new();
+    v.shrink_to_fit().unwrap();
+    assert_eq!(v.capacity(), 0);
+    assert!(!v.path().exists());
+
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    v.clear();
+    assert!(v.path().exists());
+
+    // Vec is back to a null segment, like a fresh one
+    v.shrink_to_fit().unwrap();
+    assert_eq!(v.capacity(), 0);
+    assert_eq!(v.disk_size(), 0);
+    assert_eq!(&v[..], &[] as &[u64]);
+    assert!(!v.path().exists());
+
+    v.push(1).unwrap();
+    assert_eq!(&v[..], [1]);
+    assert_eq!(
+        std::fs::metadata(v.path()).unwrap().len(),
+        v.disk_size() as u64
+    );
+}
+
+#[test]
+fn test_push_after_truncate_does_not_remap() {
+    let mut v = MmapVec::<i32>::with_capacity(100).unwrap();
+    while v.len() < v.capacity() {
+        v.push(0).unwrap();
+    }
+    let addr = v.as_ptr();
+
+    // Free some space at the end of the vec.
+    v.truncate(10);
+    assert_eq!(v.capacity(), 100);
+
+    // Reserve within freed space is a no-op.
+    v.reserve(90).unwrap();
+    assert_eq!(v.capacity(), 100);
+    assert_eq!(v.as_ptr(), addr);
+
+    // Fill it again and check segment has not been remapped.
+    while v.len() < v.capacity() {
+        v.push(1).unwrap();
+    }
+    assert_eq!(v.len(), 100);
+    assert_eq!(v.capacity(), 100);
+    assert_eq!(v.as_ptr(), addr);
+
+    // Only next push should trigger growth.
+    v.push(2).unwrap();
+    assert!(v.capacity() > 100);
+}
+
+#[test]
+fn test_advice_free() {
+    let mut v = MmapVec::<i32>::new();
+    v.advice_free_range(0..10);
+    assert_eq!(v.resident_pages().unwrap(), 0);
+
+    for i in 0..5000 {
+        v.push(i).unwrap();
+    }
+    assert!(v.resident_pages().unwrap() >= 5);
+    v.advice_free_range(1000..4000);
+    v.advice_free_range(0..v.capacity());
+    assert!(v.iter().enumerate().all(|(i, x)| *x == i as i32));
+}
+
+#[test]
+fn test_numeric_helpers() {
+    // Empty vec
+    let v = MmapVec::<i64>::new();
+    assert_eq!(v.sum(), 0);
+    assert_eq!(v.min(), None);
+    assert_eq!(v.max(), None);
+    assert_eq!(v.mean(), None);
+
+    // Vec spanning many prefetch blocks
+    let v = MmapVec::<i64>::try_from((-500_000..500_000).collect::<Vec<_>>()).unwrap();
+    assert_eq!(v.sum(), -500_000);
+    assert_eq!(v.min(), Some(-500_000));
+    assert_eq!(v.max(), Some(499_999));
+    assert_eq!(v.mean(), Some(-0.5));
+
+    // NaN are skipped by min / max
+    let v = MmapVec::<f64>::try_from([f64::NAN, 2.5, f64::NAN, -1.0, 4.0]).unwrap();
+    assert_eq!(v.min(), Some(-1.0));
+    assert_eq!(v.max(), Some(4.0));
+    assert!(v.sum().is_nan());
+
+    let v = MmapVec::<f32>::try_from([1.0, 2.0]).unwrap();
+    assert_eq!(v.sum(), 3.0);
+    assert_eq!(v.mean(), Some(1.5));
+}
+
+#[test]
+fn test_index_of() {
+    // Empty vec
+    let v = MmapVec::<DataRow>::new();
+    assert_eq!(v.index_of(&ROW1), None);
+
+    let mut v = MmapVec::<DataRow>::try_from([ROW1, ROW2, ROW1]).unwrap();
+    for (i, row) in v.iter().enumerate() {
+        assert_eq!(v.index_of(row), Some(i));
+    }
+
+    // Equal values out of vec are not found
+    assert_eq!(v.index_of(&ROW1), None);
+    let other = v.try_clone().unwrap();
+    assert_eq!(v.index_of(&other[0]), None);
+
+    // Spare capacity is not part of the vec
+    let last = &v[2] as *const DataRow;
+    v.truncate(2);
+    assert_eq!(v.index_of(unsafe { &*last }), None);
+}
+
+#[test]
+fn test_map_to_vec() {
+    // Empty vec
+    let v = MmapVec::<DataRow>::new();
+    let volumes = v.map_to_vec(|row| row.volume).unwrap();
+    assert!(volumes.is_empty());
+
+    // Vec spanning many prefetch blocks
+    let mut v = MmapVec::<DataRow>::new();
+    for i in 0..100_000 {
+        v.push(DataRow {
+            bid: i as f64,
+            ask: 1.0,
+            volume: i,
+        })
+        .unwrap();
+    }
+    let volumes = v.map_to_vec(|row| row.volume).unwrap();
+    assert_eq!(volumes.capacity(), 100_000);
+    assert_ne!(volumes.path(), v.path());
+    assert!(volumes.iter().copied().eq(0..100_000));
+
+    let spreads = v.map_to_vec(|row| row.bid - row.ask).unwrap();
+    assert_eq!(spreads.len(), 100_000);
+    assert_eq!(spreads[10], 9.0);
+}
+
+#[test]
+fn test_advise() {
+    let patterns = [
+        AccessPattern::WillNeed,
+        AccessPattern::DontNeed,
+        AccessPattern::Sequential,
+        AccessPattern::Random,
+    ];
+
+    // Null segment
+    let v = MmapVec::<i32>::new();
+    for pattern in patterns {
+        v.advise(pattern);
+    }
+
+    // Data is kept whatever the advice
+    let v = MmapVec::<i32>::try_from((0..5000).collect::<Vec<_>>()).unwrap();
+    for pattern in patterns {
+        v.advise(pattern);
+        assert!(v.iter().copied().eq(0..5000));
+    }
+}
+
+#[test]
+fn test_assume_init_len() {
+    let mut v = MmapVec::<u64>::with_capacity(100).unwrap();
+    unsafe { v.assume_init_len(0) };
+    assert!(v.is_empty());
+
+    // Reopen segment file written by another vec
+    for i in 0..3 {
+        v.push(i + 1).unwrap();
+    }
+    v.flush().unwrap();
+    let builder = DefaultSegmentBuilder::default();
+    let path = builder.new_segment_path();
+    std::fs::copy(v.path(), &path).unwrap();
+
+    let segment = Segment::<u64>::open_rw(&path, v.capacity()).unwrap();
+    let mut reopened = unsafe { MmapVec::from_segment(segment, builder, path) };
+    assert!(reopened.is_empty());
+    unsafe { reopened.assume_init_len(3) };
+    assert_eq!(&reopened[..], [1, 2, 3]);
+    reopened.push(4).unwrap();
+    assert_eq!(&reopened[..], [1, 2, 3, 4]);
+}
+
+#[test]
+#[should_panic = "initialized len (is 513) should be <= capacity (is 512)"]
+fn test_assume_init_len_above_capacity() {
+    let mut v = MmapVec::<u64>::with_capacity(512).unwrap();
+    unsafe { v.assume_init_len(513) };
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_truncate_and_punch() {
+    use std::os::unix::fs::MetadataExt;
+
+    let blocks = |v: &MmapVec<u64>| std::fs::metadata(v.path()).unwrap().blocks();
+
+    // Trigger on null segment
+    let mut v = MmapVec::<u64>::new();
+    v.truncate_and_punch(0).unwrap();
+
+    for i in 0..100_000 {
+        v.push(i).unwrap();
+    }
+    v.flush().unwrap();
+    let capacity = v.capacity();
+    let file_size = std::fs::metadata(v.path()).unwrap().len();
+    let full_blocks = blocks(&v);
+
+    v.truncate_and_punch(1000).unwrap();
+    assert_eq!(v.len(), 1000);
+    assert_eq!(v.capacity(), capacity);
+    assert!(v.iter().copied().eq(0..1000));
+    assert_eq!(std::fs::metadata(v.path()).unwrap().len(), file_size);
+    assert!(blocks(&v) < full_blocks);
+
+    // Punched range can be written again without remap
+    for i in 1000..100_000 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.capacity(), capacity);
+    assert!(v.iter().copied().eq(0..100_000));
+}
+
+#[test]
+fn test_clear_and_evict() {
+    let mut v = MmapVec::<DroppableRow>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Trigger on null segment
+    v.clear_and_evict();
+    assert_eq!(v.capacity(), 0);
+
+    for _ in 0..500 {
+        v.push(DroppableRow::new(counter.clone())).unwrap();
+    }
+    let capacity = v.capacity();
+    let addr = v.as_ptr();
+
+    v.clear_and_evict();
+    assert_eq!(v.len(), 0);
+    assert_eq!(v.capacity(), capacity);
+    assert_eq!(counter.load(Ordering::Relaxed), 500);
+
+    // Refill does not remap
+    for _ in 0..capacity {
+        v.push(DroppableRow::new(counter.clone())).unwrap();
+    }
+    assert_eq!(v.capacity(), capacity);
+    assert_eq!(v.as_ptr(), addr);
+}
+
+#[test]
+fn test_fill_from_reader() {
+    let bytes: Vec<u8> = (0..2000u64).flat_map(|x| x.to_ne_bytes()).collect();
+
+    // Fill empty vec
+    let mut v = MmapVec::<u64>::new();
+    v.fill_from_reader(&bytes[..8 * 1500], 1500).unwrap();
+    assert_eq!(v.len(), 1500);
+    assert!(v.iter().enumerate().all(|(i, x)| *x == i as u64));
+
+    // Append to existing data
+    v.fill_from_reader(&bytes[8 * 1500..], 500).unwrap();
+    assert_eq!(v.len(), 2000);
+    assert!(v.iter().enumerate().all(|(i, x)| *x == i as u64));
+
+    // Read nothing
+    v.fill_from_reader(&bytes[..0], 0).unwrap();
+    assert_eq!(v.len(), 2000);
+
+    // Short read
+    let err = v.fill_from_reader(&bytes[..15], 2).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(v.len(), 2000);
+}
+
+#[test]
+fn test_write_to() {
+    // Write empty vec
+    let v = MmapVec::<u64>::new();
+    let mut bytes = Vec::new();
+    v.write_to(&mut bytes).unwrap();
+    assert!(bytes.is_empty());
+
+    // Write vec with data
+    let v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    v.write_to(&mut bytes).unwrap();
+    assert_eq!(
+        bytes,
+        [1u64, 2, 3]
+            .iter()
+            .flat_map(|x| x.to_ne_bytes())
+            .collect::<Vec<_>>()
+    );
+
+    // Read it back
+    let mut other = MmapVec::<u64>::new();
+    other.fill_from_reader(bytes.as_slice(), 3).unwrap();
+    assert_eq!(other, v);
+}
+
+#[test]
+fn test_as_slice_of() {
+    // Empty vec
+    let v = MmapVec::<u8>::new();
+    assert_eq!(v.as_slice_of::<u32>(), Some(&[][..]));
+
+    // Bytes to bigger values
+    let bytes: Vec<u8> = (0..6u32).flat_map(|x| x.to_ne_bytes()).collect();
+    let v = MmapVec::<u8>::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(v.as_slice_of::<u32>(), Some(&[0, 1, 2, 3, 4, 5][..]));
+    assert_eq!(v.as_slice_of::<u64>().map(|x| x.len()), Some(3));
+    assert_eq!(v.as_slice_of::<[u32; 2]>().map(|x| x[1]), Some([2, 3]));
+    assert_eq!(v.as_slice_of::<u8>(), Some(bytes.as_slice()));
+
+    // Size mismatch
+    assert_eq!(v.as_slice_of::<[u8; 5]>(), None);
+    assert_eq!(v.as_slice_of::<u128>(), None);
+
+    // Zero sized target
+    assert_eq!(v.as_slice_of::<[u32; 0]>(), None);
+}
+
+#[test]
+fn test_is_well_aligned_for() {
+    #[repr(align(4096))]
+    struct PageAligned;
+
+    #[repr(align(8192))]
+    struct TwoPagesAligned;
+
+    // Empty vec is only aligned for its own type
+    let v = MmapVec::<u8>::new();
+    assert!(v.is_well_aligned_for::<u8>());
+
+    // Mapping is page aligned
+    let v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    assert!(v.is_well_aligned_for::<u8>());
+    assert!(v.is_well_aligned_for::<u16>());
+    assert!(v.is_well_aligned_for::<u64>());
+    assert!(v.is_well_aligned_for::<u128>());
+    assert!(v.is_well_aligned_for::<PageAligned>());
+    assert_eq!(
+        v.is_well_aligned_for::<TwoPagesAligned>(),
+        v.as_ptr() as usize % 8192 == 0
+    );
+}
+
+#[test]
+fn test_resize_len() {
+    let mut v = MmapVec::<DroppableRow>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Grow from null vec
+    v.resize(3, DroppableRow::new(counter.clone())).unwrap();
+    assert_eq!(v.len(), 3);
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+    // Same len
+    v.set_len_filled(3, DroppableRow::new(counter.clone()))
+        .unwrap();
+    assert_eq!(v.len(), 3);
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+    // Grow above capacity
+    let capacity = v.capacity();
+    v.resize(capacity + 10, DroppableRow::new(counter.clone()))
+        .unwrap();
+    assert_eq!(v.len(), capacity + 10);
+    let capacity = capacity as u32;
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+    // Shrink
+    v.resize(1, DroppableRow::new(counter.clone())).unwrap();
+    assert_eq!(v.len(), 1);
+    assert_eq!(counter.load(Ordering::Relaxed), 2 + capacity + 9);
+
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 3 + capacity + 9);
+}
+
+#[test]
+fn test_invalid_store_dir() {
+    let dir = PathBuf::from("/this/dir/does/not/exist");
+    let builder = DefaultSegmentBuilder::with_path(&dir);
+
+    // Nothing is created by `new`, error comes with first push.
+    let mut v = MmapVec::<u64, _>::with_builder(builder.clone());
+    let err = v.push(1).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err
+        .to_string()
+        .starts_with("invalid store folder /this/dir/does/not/exist: io error on "));
+    let inner = err.get_ref().unwrap().downcast_ref::<MmapVecError>();
+    assert!(matches!(inner, Some(MmapVecError::InvalidStoreDir { path, .. }) if *path == dir));
+    assert!(v.is_empty());
+
+    // Same with builder.
+    let err = MmapVecBuilder::<u64, _>::new()
+        .segment_builder(builder)
+        .capacity(10)
+        .try_build()
+        .unwrap_err();
+    let inner = err.get_ref().unwrap().downcast_ref::<MmapVecError>();
+    assert!(matches!(inner, Some(MmapVecError::InvalidStoreDir { path, .. }) if *path == dir));
+
+    // Store folder is a file.
+    let file = PathBuf::from("test_invalid_store_dir.txt");
+    std::fs::write(&file, b"").unwrap();
+    let mut v = MmapVec::<u64, _>::with_builder(DefaultSegmentBuilder::with_path(&file));
+    let err = v.push(1).unwrap_err();
+    let inner = err.get_ref().unwrap().downcast_ref::<MmapVecError>();
+    assert!(matches!(inner, Some(MmapVecError::InvalidStoreDir { path, .. }) if *path == file));
+    std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_adopt_segment() {
+    let path = PathBuf::from("test_adopt_segment.seg");
+    let mut segment = Segment::<i32>::open_rw(&path, 10).unwrap();
+    segment.push_within_capacity(4).unwrap();
+    segment.push_within_capacity(-8).unwrap();
+
+    let mut v = unsafe { MmapVec::adopt(segment, path.clone()) };
+    assert_eq!(v.path(), path);
+    assert_eq!(v.capacity(), 10);
+    assert_eq!(&v[..], [4, -8]);
+
+    // Check data are kept when growing.
+    v.reserve(100).unwrap();
+    v.push(7).unwrap();
+    assert_eq!(&v[..], [4, -8, 7]);
+
+    // Check file is removed once.
+    drop(v);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_from_segment() {
+    let builder = DefaultSegmentBuilder::default();
+    let path = builder.new_segment_path();
+    let segment = Segment::<i32>::open_rw(&path, 10).unwrap();
+
+    let v = unsafe { MmapVec::from_segment(segment, builder, path.clone()) };
+    assert_eq!(v.capacity(), 10);
+    assert!(path.exists());
+
+    drop(v);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_from_raw_parts() {
+    let path = PathBuf::from("test_from_raw_parts.seg");
+    let mut segment = Segment::<i32>::open_rw(&path, 10).unwrap();
+    segment.push_within_capacity(4).unwrap();
+    segment.push_within_capacity(-8).unwrap();
+    let addr = segment.as_mut_ptr();
+    std::mem::forget(segment);
+
+    let mut v = unsafe { MmapVec::from_raw_parts(addr, 2, 10, path.clone()) };
+    assert_eq!(v.as_ptr(), addr as *const i32);
+    assert_eq!(v.capacity(), 10);
+    assert_eq!(&v[..], [4, -8]);
+
+    // Check data are kept when growing.
+    v.reserve(100).unwrap();
+    v.push(7).unwrap();
+    assert_eq!(&v[..], [4, -8, 7]);
+
+    drop(v);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_from_raw_parts_null() {
+    let path = PathBuf::from("test_from_raw_parts_null.seg");
+    let mut v = unsafe { MmapVec::<i32>::from_raw_parts(std::ptr::null_mut(), 0, 0, path.clone()) };
+    assert!(v.is_empty());
+
+    v.push(1).unwrap();
+    assert_eq!(&v[..], [1]);
+    assert_eq!(v.path(), path);
+    drop(v);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_raw_parts_round_trip() {
+    let mut v = MmapVec::<i32>::with_capacity(10).unwrap();
+    v.push(4).unwrap();
+    v.push(-8).unwrap();
+    let ptr = v.as_ptr();
+
+    let (addr, len, capacity, path) = v.into_raw_parts();
+    assert_eq!(addr as *const i32, ptr);
+    assert_eq!((len, capacity), (2, 10));
+    assert!(path.exists());
+
+    let mut v = unsafe { MmapVec::from_raw_parts(addr, len, capacity, path.clone()) };
+    assert_eq!(&v[..], [4, -8]);
+
+    // Check data are kept when growing.
+    v.reserve(100).unwrap();
+    v.push(7).unwrap();
+    assert_eq!(&v[..], [4, -8, 7]);
+
+    drop(v);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_raw_parts_empty() {
+    let (addr, len, capacity, path) = MmapVec::<i32>::new().into_raw_parts();
+    assert!(addr.is_null());
+    assert_eq!((len, capacity), (0, 0));
+
+    let mut v = unsafe { MmapVec::from_raw_parts(addr, len, capacity, path) };
+    v.push(1).unwrap();
+    assert_eq!(&v[..], [1]);
+}
+
+#[test]
+fn test_clone_into() {
+    let src = MmapVec::<i32>::try_from([1, 2, 3, 4]).unwrap();
+
+    // Target is bigger
+    {
+        let mut dst = MmapVec::<i32>::try_from([9, 8, 7, 6, 5, 4]).unwrap();
+        let addr = dst.as_ptr();
+        let path = dst.path();
+
+        src.clone_into(&mut dst).unwrap();
+        assert_eq!(&dst[..], [1, 2, 3, 4]);
+        assert_eq!(dst.capacity(), 6);
+        assert_eq!(dst.as_ptr(), addr);
+        assert_eq!(dst.path(), path);
+    }
+
+    // Target is smaller but has enough capacity
+    {
+        let mut dst = MmapVec::<i32>::with_capacity(10).unwrap();
+        dst.push(5).unwrap();
+        let addr = dst.as_ptr();
+
+        src.clone_into(&mut dst).unwrap();
+        assert_eq!(&dst[..], [1, 2, 3, 4]);
+        assert_eq!(dst.capacity(), 10);
+        assert_eq!(dst.as_ptr(), addr);
+    }
+
+    // Target is too small
+    {
+        let mut dst = MmapVec::<i32>::new();
+        src.clone_into(&mut dst).unwrap();
+        assert_eq!(&dst[..], [1, 2, 3, 4]);
+    }
+
+    // Source is empty
+    {
+        let mut dst = MmapVec::<i32>::try_from([1, 2]).unwrap();
+        MmapVec::<i32>::new().clone_into(&mut dst).unwrap();
+        assert_eq!(&dst[..], []);
+    }
+}
+
+#[test]
+fn test_clone_into_drop() {
+    let counter = Arc::new(AtomicU32::new(0));
+    let src = MmapVec::<DroppableRow>::try_from(vec![DroppableRow::new(counter.clone())]).unwrap();
+
+    let mut dst = MmapVec::<DroppableRow>::try_from(vec![
+        DroppableRow::new(counter.clone()),
+        DroppableRow::new(counter.clone()),
+    ])
+    .unwrap();
+
+    // One overwritten, one truncated
+    src.clone_into(&mut dst).unwrap();
+    assert_eq!(dst.len(), 1);
+    assert_eq!(counter.load(Ordering::Relaxed), 2);
+
+    drop(dst);
+    drop(src);
+    assert_eq!(counter.load(Ordering::Relaxed), 4);
+}
+
+#[test]
+fn test_push_elements_bigger_than_page() {
+    #[derive(Clone, Copy)]
+    struct BigRow([u8; 5000]);
+
+    let mut v = MmapVec::<BigRow>::new();
+    for i in 0..100 {
+        v.push(BigRow([i; 5000])).unwrap();
+        assert!(v.capacity() > i as usize);
+        assert_eq!(v.disk_size(), v.capacity() * 5000);
+    }
+    assert_eq!(v.capacity(), 128);
+    assert!(v
+        .iter()
+        .enumerate()
+        .all(|(i, row)| row.0 == [i as u8; 5000]));
+
+    v.reserve(30).unwrap();
+    assert_eq!(v.capacity(), 130);
+}
+
+#[test]
+fn test_try_extend() {
+    let counter = Arc::new(AtomicU32::new(0));
+    let mut v = MmapVecBuilder::<DroppableRow>::new()
+        .capacity(0)
+        .segment_size_limit(10 * std::mem::size_of::<DroppableRow>())
+        .try_build()
+        .unwrap();
+
+    // Empty iterator
+    assert_eq!(v.try_extend(std::iter::empty()).unwrap(), 0);
+
+    let rows = |n| (0..n).map(|_| DroppableRow::new(counter.clone()));
+    assert_eq!(v.try_extend(rows(4)).unwrap(), 4);
+    assert_eq!(v.len(), 4);
+
+    // Stops on first error, keeping values already pushed
+    let mut iter = rows(20);
+    let (pushed, err) = v.try_extend(iter.by_ref()).unwrap_err();
+    assert_eq!(pushed, 6);
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(v.len(), 10);
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+    // Iterator is not polled anymore
+    assert_eq!(iter.count(), 13);
+
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 24);
+}
+
+#[test]
+fn test_append_exact() {
+    // Empty iterator on null segment
+    let mut v = MmapVec::<u32>::new();
+    v.append_exact(std::iter::empty()).unwrap();
+    assert_eq!(v.capacity(), 0);
+
+    // Capacity is reserved once, instead of doubling
+    let mut v = MmapVec::<u32>::try_from([0, 1]).unwrap();
+    v.append_exact(2..1_000_000).unwrap();
+    assert!(v.iter().copied().eq(0..1_000_000));
+    assert_eq!(v.capacity(), 1_000_448);
+
+    // Nothing is pushed when reserve fails
+    let mut v = MmapVecBuilder::<u32>::new()
+        .capacity(0)
+        .segment_size_limit(80)
+        .try_build()
+        .unwrap();
+    v.append_exact(0..5).unwrap();
+    assert!(v.append_exact(0..20).is_err());
+    assert_eq!(&v[..], [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_reserve_exact() {
+    // Test on null segment
+    let mut s = MmapVec::<DataRow>::new();
+    s.reserve_exact(3).unwrap();
+    assert_eq!(s.capacity(), 3);
+    assert_eq!(s.disk_size(), 3 * 24);
+
+    // Test with free space
+    s.push(ROW1).unwrap();
+    s.reserve_exact(2).unwrap();
+    assert_eq!(s.capacity(), 3);
+
+    // Test grow
+    s.reserve_exact(5).unwrap();
+    assert_eq!(s.capacity(), 6);
+    assert_eq!(s.disk_size(), 6 * 24);
+    assert_eq!(&s[..], [ROW1]);
+
+    // Check file size
+    assert_eq!(std::fs::metadata(s.path()).unwrap().len(), 6 * 24);
+}
+
+#[test]
+fn test_reserve_returning_capacity() {
+    // Test on null segment
+    let mut s = MmapVec::<DataRow>::new();
+    assert_eq!(s.reserve_returning_capacity(3).unwrap(), 170);
+
+    // Test with free space
+    s.push(ROW1).unwrap();
+    assert_eq!(s.reserve_returning_capacity(169).unwrap(), 170);
+
+    // Test grow
+    let capacity = s.reserve_returning_capacity(170).unwrap();
+    assert_eq!(capacity, 340);
+    assert_eq!(s.capacity(), capacity);
+    assert_eq!(&s[..], [ROW1]);
+
+    // Errors are forwarded
+    assert!(s.reserve_returning_capacity(usize::MAX).is_err());
+    assert_eq!(s.capacity(), 340);
+}
+
+#[test]
+fn test_reserve_and_prefault() {
+    // Test on null segment
+    let mut s = MmapVec::<DataRow>::new();
+    s.reserve_and_prefault(3).unwrap();
+    assert_eq!(s.capacity(), 170);
+
+    // Test with free space
+    s.push(ROW1).unwrap();
+    s.push(ROW2).unwrap();
+    s.reserve_and_prefault(2).unwrap();
+    assert_eq!(s.capacity(), 170);
+
+    // Test grow, data is left unchanged
+    s.reserve_and_prefault(1000).unwrap();
+    assert_eq!(s.capacity(), 1020);
+    assert_eq!(&s[..], [ROW1, ROW2]);
+
+    for _ in 2..1020 {
+        s.push(ROW3).unwrap();
+    }
+    assert_eq!(s.capacity(), 1020);
+    assert_eq!(s[1019], ROW3);
+}
+
+#[test]
+fn test_advice_prefetch_strided() {
+    let mut v = MmapVec::<u64>::new();
+    v.advice_prefetch_strided(0, 100, 10);
+
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    v.advice_prefetch_strided(0, 1, 10_000);
+    v.advice_prefetch_strided(5, 1000, 10);
+    v.advice_prefetch_strided(9_999, 1000, 10);
+    v.advice_prefetch_strided(0, 0, 10);
+    v.advice_prefetch_strided(0, 100, 0);
+}
+
+#[test]
+fn test_snapshot_to() {
+    let dest = PathBuf::from("test_snapshot_to.seg");
+
+    // Snapshot empty vec
+    let mut v = MmapVec::<u64>::new();
+    v.snapshot_to(&dest).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), []);
+
+    // Snapshot vec with data
+    v.push(1).unwrap();
+    v.push(2).unwrap();
+    v.snapshot_to(&dest).unwrap();
+    let mut snapshot = MmapVec::<u64>::new();
+    snapshot
+        .fill_from_reader(std::fs::File::open(&dest).unwrap(), 2)
+        .unwrap();
+    assert_eq!(&snapshot[..], [1, 2]);
+
+    // Original vec keeps working
+    v.push(3).unwrap();
+    assert_eq!(&v[..], [1, 2, 3]);
+    assert_eq!(std::fs::metadata(&dest).unwrap().len(), 16);
+
+    // Bad destination
+    let err = v.snapshot_to("/this/dir/does/not/exist.seg").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    std::fs::remove_file(&dest).unwrap();
+}
+
+#[test]
+fn test_files_equal() {
+    let a = PathBuf::from("test_files_equal_a.seg");
+    let b = PathBuf::from("test_files_equal_b.seg");
+
+    // Empty files
+    MmapVec::<u64>::new().snapshot_to(&a).unwrap();
+    MmapVec::<u64>::new().snapshot_to(&b).unwrap();
+    assert!(mmap_vec::files_equal(&a, &b).unwrap());
+
+    // Snapshot matches its source, spanning several compare blocks
+    let mut v = MmapVec::<u64>::new();
+    for i in 0..20_480 {
+        v.push(i).unwrap();
+    }
+    v.shrink_to_fit().unwrap();
+    assert_eq!(v.disk_size(), 163_840);
+    v.snapshot_to(&a).unwrap();
+    assert!(mmap_vec::files_equal(&a, v.path()).unwrap());
+
+    // Same size but different content, far from file start
+    v[19_000] = 0;
+    v.snapshot_to(&b).unwrap();
+    assert!(!mmap_vec::files_equal(&a, &b).unwrap());
+
+    // Different sizes
+    v.pop();
+    v.snapshot_to(&b).unwrap();
+    assert!(!mmap_vec::files_equal(&a, &b).unwrap());
+    assert!(!mmap_vec::files_equal(&b, &a).unwrap());
+
+    // Missing file
+    let err = mmap_vec::files_equal(&a, "/this/dir/does/not/exist.seg").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    std::fs::remove_file(&a).unwrap();
+    std::fs::remove_file(&b).unwrap();
+}
+
+#[test]
+fn test_save_load() {
+    let path = PathBuf::from("test_save_load.bin");
+
+    // Empty vec
+    let v = MmapVec::<u64>::new();
+    v.save(&path).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 32);
+    let loaded = MmapVec::<u64>::load(&path).unwrap();
+    assert!(loaded.is_empty());
+
+    // Vec with data
+    let v = MmapVec::<[u64; 3]>::try_from([[1, 2, 3], [4, 5, 6], [7, 8, 9]]).unwrap();
+    v.save(&path).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 32 + 3 * 24);
+    let loaded = MmapVec::<[u64; 3]>::load(&path).unwrap();
+    assert_eq!(loaded, v);
+    assert_ne!(loaded.path(), v.path());
+
+    // File is kept after load and vec drop
+    drop(loaded);
+    assert!(path.exists());
+
+    // Wrong element type
+    let err = MmapVec::<u64>::load(&path).unwrap_err();
+    assert!(matches!(
+        err,
+        MmapVecError::ElementSizeMismatch {
+            file_element_size: 24,
+            element_size: 8
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "element size mismatch: file contains 24 bytes elements, expected 8"
+    );
+
+    // Truncated file
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+    assert!(matches!(
+        MmapVec::<[u64; 3]>::load(&path),
+        Err(MmapVecError::InvalidHeader {
+            reason: "file size does not match header len"
+        })
+    ));
+    std::fs::write(&path, &bytes[..10]).unwrap();
+    assert!(matches!(
+        MmapVec::<[u64; 3]>::load(&path),
+        Err(MmapVecError::InvalidHeader {
+            reason: "file is too small"
+        })
+    ));
+
+    // Not a saved vec
+    std::fs::write(&path, [0u8; 64]).unwrap();
+    let err = MmapVec::<u64>::load(&path).unwrap_err();
+    assert_eq!(err.to_string(), "invalid header: bad magic bytes");
+    let err = io::Error::from(err);
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // Bad version
+    let mut bytes = bytes;
+    bytes[8] = 42;
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(matches!(
+        MmapVec::<[u64; 3]>::load(&path),
+        Err(MmapVecError::InvalidHeader {
+            reason: "unsupported version"
+        })
+    ));
+
+    std::fs::remove_file(&path).unwrap();
+
+    // Missing file
+    assert!(matches!(
+        MmapVec::<u64>::load(&path),
+        Err(MmapVecError::FileIo { .. })
+    ));
+}
+
+#[test]
+fn test_push_pop_at_capacity_boundary() {
+    let mut v = MmapVec::<u64>::new();
+    assert_eq!(v.grow_count(), 0);
+
+    // Fill vec up to its first capacity.
+    v.push(0).unwrap();
+    while v.len() < v.capacity() {
+        v.push(0).unwrap();
+    }
+    let capacity = v.capacity();
+    assert_eq!(v.grow_count(), 1);
+
+    for _ in 0..10_000 {
+        assert!(v.pop().is_some());
+        v.push(1).unwrap();
+    }
+
+    assert_eq!(v.capacity(), capacity);
+    assert_eq!(v.grow_count(), 1);
+}
+
+#[test]
+fn test_push_burst_grows_geometrically() {
+    let mut v = MmapVec::<u64>::new();
+    let mut capacities = Vec::new();
+    for i in 0..1_000_000 {
+        v.push(i).unwrap();
+        if capacities.last() != Some(&v.capacity()) {
+            capacities.push(v.capacity());
+        }
+    }
+
+    // First grow maps one page, then capacity doubles.
+    assert_eq!(capacities[0], mmap_vec::round_capacity_to_page::<u64>(1));
+    for w in capacities.windows(2) {
+        assert_eq!(w[1], 2 * w[0]);
+    }
+    assert_eq!(v.grow_count(), capacities.len() as u64);
+}
+
+#[test]
+fn test_slice_prefetched() {
+    let mut v = MmapVec::<u64>::new();
+    assert_eq!(v.slice_prefetched(0..10), []);
+
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.slice_prefetched(0..3), [0, 1, 2]);
+    assert_eq!(v.slice_prefetched(4000..9000), &v[4000..9000]);
+    assert_eq!(v.slice_prefetched(9998..20_000), [9998, 9999]);
+    assert_eq!(v.slice_prefetched(20_000..30_000), []);
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = usize::MAX..0;
+    assert_eq!(v.slice_prefetched(reversed), []);
+}
+
+#[test]
+fn test_find_prefetched() {
+    let mut v = MmapVec::<u64>::new();
+    assert_eq!(v.find_prefetched(|_| true), None);
+
+    for i in 0..100_000 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.find_prefetched(|x| *x == 0), Some(0));
+    assert_eq!(v.find_prefetched(|x| *x == 8191), Some(8191));
+    assert_eq!(v.find_prefetched(|x| *x == 8192), Some(8192));
+    assert_eq!(v.find_prefetched(|x| *x >= 54_321), Some(54_321));
+    assert_eq!(v.find_prefetched(|x| *x == 99_999), Some(99_999));
+    assert_eq!(v.find_prefetched(|x| *x == 100_000), None);
+
+    // Predicate is called once per element until match
+    let mut count = 0;
+    v.find_prefetched(|x| {
+        count += 1;
+        *x == 20_000
+    });
+    assert_eq!(count, 20_001);
+}
+
+#[test]
+fn test_iter_indexed_prefetched() {
+    let mut v = MmapVec::<u64>::new();
+    assert_eq!(v.iter_indexed_prefetched(1).count(), 0);
+
+    for i in 0..100_000 {
+        v.push(i * 2).unwrap();
+    }
+
+    for lookahead_pages in [0, 1, 3, 16, usize::MAX] {
+        let iter = v.iter_indexed_prefetched(lookahead_pages);
+        assert_eq!(iter.len(), 100_000);
+        assert!(iter.eq(v.iter().enumerate()));
+    }
+
+    let mut iter = v.iter_indexed_prefetched(2);
+    assert_eq!(iter.nth(70_000), Some((70_000, &140_000)));
+    assert_eq!(iter.len(), 29_999);
+}
+
+#[test]
+fn test_range_to_vec() {
+    let mut v = MmapVec::<DataRow>::new();
+    assert_eq!(v.range_to_vec(..), []);
+    assert_eq!(v.range_to_vec(0..10), []);
+
+    v.push(ROW1).unwrap();
+    v.push(ROW2).unwrap();
+    v.push(ROW3).unwrap();
+    v.push(ROW4).unwrap();
+
+    assert_eq!(v.range_to_vec(..), [ROW1, ROW2, ROW3, ROW4]);
+    assert_eq!(v.range_to_vec(1..3), [ROW2, ROW3]);
+    assert_eq!(v.range_to_vec(1..=2), [ROW2, ROW3]);
+    assert_eq!(v.range_to_vec(..2), [ROW1, ROW2]);
+    assert_eq!(v.range_to_vec(3..), [ROW4]);
+    assert_eq!(v.range_to_vec(2..100), [ROW3, ROW4]);
+    assert_eq!(v.range_to_vec(..=usize::MAX), [ROW1, ROW2, ROW3, ROW4]);
+    assert_eq!(v.range_to_vec(100..200), []);
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = 3..1;
+    assert_eq!(v.range_to_vec(reversed), []);
+}
+
+#[test]
+fn test_move_to_dir() {
+    let dir1 = PathBuf::from("/tmp/test_move_to_dir_1");
+    let dir2 = std::env::current_dir().unwrap().join("test_move_to_dir_2");
+    std::fs::create_dir_all(&dir1).unwrap();
+    std::fs::create_dir_all(&dir2).unwrap();
+
+    // Move null vec
+    let mut v = MmapVec::<i32>::new();
+    v.move_to_dir(&dir1).unwrap();
+    assert!(v.path().starts_with(&dir1));
+    v.push(42).unwrap();
+    assert!(v.path().exists());
+
+    // Move vec with data (may be cross device)
+    for _ in 0..2000 {
+        v.push(8).unwrap();
+    }
+    let old_path = v.path();
+    v.move_to_dir(&dir2).unwrap();
+    assert!(v.path().starts_with(&dir2));
+    assert_eq!(v.path().file_name(), old_path.file_name());
+    assert!(!old_path.exists());
+    assert!(v.path().exists());
+    assert_eq!(v.len(), 2001);
+    assert_eq!(v[0], 42);
+    assert!(v[1..].iter().all(|x| *x == 8));
+
+    // Move on same device
+    v.move_to_dir(&dir2).unwrap();
+    assert_eq!(v.len(), 2001);
+
+    // Vec still working
+    v.push(-5).unwrap();
+    assert_eq!(v[2001], -5);
+
+    // Move to missing dir
+    assert!(v.move_to_dir("/this/dir/does/not/exist").is_err());
+    assert!(v.path().exists());
+    assert_eq!(v.len(), 2002);
+
+    let path = v.path();
+    drop(v);
+    assert!(!path.exists());
+    std::fs::remove_dir(&dir2).unwrap();
+}
+
+#[test]
+fn test_capacity_overflow() {
+    for capacity in [
+        usize::MAX,
+        usize::MAX / 24 + 1,
+        isize::MAX as usize / 24 + 1,
+    ] {
+        let err = MmapVec::<DataRow>::with_capacity(capacity).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            format!("capacity overflow: {capacity} elements of 24 bytes")
+        );
+    }
+
+    let mut v = MmapVec::<DataRow>::try_from([ROW1]).unwrap();
+    assert!(v.reserve(usize::MAX).is_err());
+    assert!(v.reserve_exact(usize::MAX).is_err());
+    assert!(v.reserve(usize::MAX / 2).is_err());
+    assert_eq!(&v[..], [ROW1]);
+}
+
+#[test]
+fn test_page_sized_elements() {
+    fn check<const N: usize>() {
+        let mut v = MmapVec::<[u8; N]>::new();
+
+        v.push([1; N]).unwrap();
+        assert_eq!(v.capacity(), 1);
+        v.push([2; N]).unwrap();
+        assert_eq!(v.capacity(), 2);
+        v.push([3; N]).unwrap();
+        assert_eq!(v.capacity(), 4);
+
+        v.reserve(3).unwrap();
+        assert_eq!(v.capacity(), 6);
+        v.reserve(0).unwrap();
+        assert_eq!(v.capacity(), 6);
+        assert_eq!(v.disk_size(), 6 * N);
+
+        assert_eq!(&v[..], [[1; N], [2; N], [3; N]]);
+
+        // Same from an empty mapped segment
+        let mut v = MmapVec::<[u8; N]>::with_capacity(0).unwrap();
+        assert_eq!(v.capacity(), 0);
+        v.push([1; N]).unwrap();
+        assert_eq!(v.capacity(), 1);
+        assert_eq!(&v[..], [[1; N]]);
+    }
+
+    check::<4095>();
+    check::<4096>();
+    check::<4097>();
+}
+
+#[test]
+fn test_as_mut_ptr_range() {
+    // Slice methods are available through `DerefMut`.
+    let mut v = MmapVec::<u32>::new();
+    let range = v.as_mut_ptr_range();
+    assert_eq!(range.start, range.end);
+
+    v.push(1).unwrap();
+    v.push(2).unwrap();
+    v.push(3).unwrap();
+
+    let range = v.as_mut_ptr_range();
+    assert_eq!(range.start, v.as_mut_ptr());
+    assert_eq!(range.end as usize - range.start as usize, 3 * 4);
+
+    unsafe {
+        let mut ptr = range.start;
+        while ptr < range.end {
+            *ptr *= 10;
+            ptr = ptr.add(1);
+        }
+    }
+    assert_eq!(&v[..], [10, 20, 30]);
+    assert_eq!(v.as_ptr_range().end, range.end as *const u32);
+}
+
+#[test]
+fn test_into_chunk_files() {
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Empty vec
+    let v = MmapVec::<DroppableRow>::new();
+    assert!(v.into_chunk_files(10).unwrap().is_empty());
+
+    // Check elements are moved, not dropped
+    let mut v = MmapVec::<(u32, DroppableRow)>::new();
+    for key in 0..25 {
+        v.push((key, DroppableRow::new(counter.clone()))).unwrap();
+    }
+    let path = v.path();
+
+    let chunks = v.into_chunk_files(10).unwrap();
+    assert!(!path.exists());
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(
+        chunks.iter().map(|x| x.capacity()).collect::<Vec<_>>(),
+        [10, 10, 5]
+    );
+    assert!(chunks
+        .iter()
+        .flat_map(|x| x.iter())
+        .enumerate()
+        .all(|(i, x)| x.0 == i as u32));
+    assert!(chunks.iter().all(|x| x.path().exists()));
+
+    drop(chunks);
+    assert_eq!(counter.load(Ordering::Relaxed), 25);
+
+    // Chunk len bigger than vec
+    let v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    let chunks = v.into_chunk_files(usize::MAX).unwrap();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(&chunks[0][..], [1, 2, 3]);
+}
+
+#[test]
+#[should_panic = "Chunk len cannot be 0"]
+fn test_into_chunk_files_zero() {
+    let v = MmapVec::<u8>::try_from([1, 2, 3]).unwrap();
+    let _ = v.into_chunk_files(0);
+}
+
+#[test]
+fn test_sorted_unique() {
+    let v = MmapVec::<i32>::new();
+    let sorted = v.sorted_unique().unwrap();
+    assert!(sorted.is_empty());
+
+    let v = MmapVec::<i32>::try_from([5, -2, 8, 5, 0, -2, 5, 13]).unwrap();
+    let sorted = v.sorted_unique().unwrap();
+    assert_eq!(&sorted[..], [-2, 0, 5, 8, 13]);
+    assert_ne!(sorted.path(), v.path());
+
+    // Source is left untouched
+    assert_eq!(&v[..], [5, -2, 8, 5, 0, -2, 5, 13]);
+}
+
+#[test]
+fn test_concat() {
+    // No parts
+    let v = mmap_vec::concat::<u64, DefaultSegmentBuilder>(&[]).unwrap();
+    assert!(v.is_empty());
+    assert_eq!(v.capacity(), 0);
+
+    // Only empty parts
+    let parts = [MmapVec::<u64>::new(), MmapVec::new()];
+    let v = mmap_vec::concat(&parts).unwrap();
+    assert!(v.is_empty());
+
+    // Mix of empty and non empty parts
+    let parts = [
+        MmapVec::<u64>::new(),
+        MmapVec::try_from([0, 1, 2]).unwrap(),
+        MmapVec::new(),
+        MmapVec::try_from((3..1003).collect::<Vec<_>>()).unwrap(),
+        MmapVec::try_from([1003, 1004, 1005, 1006, 1007]).unwrap(),
+        MmapVec::new(),
+    ];
+    let v = mmap_vec::concat(&parts).unwrap();
+    assert_eq!(v.len(), 1008);
+    assert_eq!(v.capacity(), 1008);
+    assert!(v.iter().copied().eq(0..1008));
+
+    // Parts are left untouched
+    assert_eq!(parts.iter().map(|part| part.len()).sum::<usize>(), 1008);
+    assert!(parts.iter().all(|part| part.path() != v.path()));
+}
+
+#[test]
+fn test_concat_drop() {
+    let counter = Arc::new(AtomicU32::new(0));
+
+    let mut parts = vec![MmapVec::<DroppableRow>::new(), MmapVec::new()];
+    for _ in 0..10 {
+        parts[0].push(DroppableRow::new(counter.clone())).unwrap();
+        parts[1].push(DroppableRow::new(counter.clone())).unwrap();
+    }
+
+    let v = mmap_vec::concat(&parts).unwrap();
+    assert_eq!(v.len(), 20);
+
+    drop(parts);
+    assert_eq!(counter.load(Ordering::Relaxed), 20);
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 40);
+}
+
+#[test]
+fn test_try_clone_into_dir() {
+    let dir = PathBuf::from("/tmp/test_try_clone_into_dir");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // Test on null segment
+    let v = MmapVec::<DataRow>::new();
+    let mut other = v.try_clone_into_dir(&dir).unwrap();
+    assert_eq!(other.capacity(), 0);
+    other.push(ROW1).unwrap();
+    assert!(other.path().starts_with(&dir));
+    assert!(other.path().exists());
+
+    // Test on empty vec with reserved capacity: no file is created
+    let v = MmapVec::<DataRow>::with_capacity(10).unwrap();
+    let other = v.try_clone_into_dir(&dir).unwrap();
+    assert_eq!(other.capacity(), 0);
+    assert!(!other.path().exists());
+
+    // Test with data
+    let mut v = MmapVec::<DataRow>::with_capacity(10).unwrap();
+    v.push(ROW1).unwrap();
+    v.push(ROW2).unwrap();
+
+    let mut other = v.try_clone_into_dir(&dir).unwrap();
+    assert_eq!(other, v);
+    assert_eq!(other.capacity(), 10);
+    assert!(other.path().starts_with(&dir));
+    assert!(!v.path().starts_with(&dir));
+
+    // Vecs are independent
+    other[0] = ROW3;
+    assert_eq!(&v[..], [ROW1, ROW2]);
+    assert_eq!(&other[..], [ROW3, ROW2]);
+
+    // Missing dir
+    assert!(v.try_clone_into_dir(dir.join("missing")).is_err());
+}
+
+#[test]
+fn test_get_unchecked() {
+    let mut v = MmapVec::<u64>::try_from((0..10_000).collect::<Vec<_>>()).unwrap();
+
+    // Bounds are checked once, outside of the loop.
+    let len = v.len();
+    let mut total = 0;
+    for i in 0..len {
+        total += unsafe { *v.get_unchecked(i) };
+    }
+    assert_eq!(total, v.sum());
+
+    for i in 0..len {
+        unsafe { *v.get_unchecked_mut(i) *= 2 };
+    }
+    assert!(v.iter().copied().eq((0..10_000).map(|x| x * 2)));
+    assert_eq!(unsafe { v.get_unchecked(1..3) }, [2, 4]);
+}
+
+#[test]
+fn test_swap_with_slice() {
+    // Slice methods are available through `DerefMut`.
+    let mut v = MmapVec::<DataRow>::try_from([ROW1, ROW2]).unwrap();
+    let mut buffer = vec![ROW3, ROW4];
+
+    v.swap_with_slice(&mut buffer);
+    assert_eq!(&v[..], [ROW3, ROW4]);
+    assert_eq!(buffer, [ROW1, ROW2]);
+
+    // Swap sub range
+    v[1..].swap_with_slice(&mut buffer[..1]);
+    assert_eq!(&v[..], [ROW3, ROW1]);
+    assert_eq!(buffer, [ROW4, ROW2]);
+}
+
+#[test]
+#[should_panic]
+fn test_swap_with_slice_bad_len() {
+    let mut v = MmapVec::<DataRow>::try_from([ROW1, ROW2]).unwrap();
+    v.swap_with_slice(&mut [ROW3]);
+}
+
+#[test]
+fn test_reserve_via_copy() {
+    let mut v = MmapVec::<DroppableRow>::new();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    // Test on null segment
+    v.reserve_via_copy(10).unwrap();
+    assert_eq!(v.capacity(), 512);
+    assert!(v.path().exists());
+
+    for _ in 0..512 {
+        v.push(DroppableRow::new(counter.clone())).unwrap();
+    }
+    let path = v.path();
+
+    // Test with free space
+    v.reserve_via_copy(0).unwrap();
+    assert_eq!(v.capacity(), 512);
+
+    // Test grow, elements are moved and not dropped
+    v.reserve_via_copy(100).unwrap();
+    assert_eq!(v.capacity(), 1024);
+    assert_eq!(v.len(), 512);
+    assert_eq!(v.path(), path);
+    assert_eq!(
+        std::fs::metadata(&path).unwrap().len(),
+        v.disk_size() as u64
+    );
+    assert!(!path.with_extension("grow").exists());
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+    drop(v);
+    assert_eq!(counter.load(Ordering::Relaxed), 512);
+    assert!(!path.exists());
+
+    // Check data integrity
+    let mut v = MmapVec::<u32>::new();
+    for i in 0..5000 {
+        v.push(i).unwrap();
+    }
+    v.reserve_via_copy(10_000).unwrap();
+    assert!(v.iter().enumerate().all(|(i, x)| *x == i as u32));
+}
+
+#[test]
+fn test_into_iter() {
+    let counter = Arc::new(AtomicU32::new(0));
+    let mut v = MmapVec::<DroppableRow>::new();
+    for _ in 0..5 {
+        assert!(v.push(DroppableRow::new(counter.clone())).is_ok());
+    }
+    let path = v.path();
+    assert!(!v.is_persistent());
+
+    let mut iter = v.into_iter();
+    assert_eq!(iter.len(), 5);
+    drop(iter.next());
+    drop(iter.next());
+    assert_eq!(counter.load(Ordering::Relaxed), 2);
+    assert_eq!(iter.len(), 3);
+
+    // Remaining values are drop with iterator, then file is removed.
+    drop(iter);
+    assert_eq!(counter.load(Ordering::Relaxed), 5);
+    assert!(!path.exists());
+
+    // Trigger on null segment
+    assert_eq!(MmapVec::<u64>::new().into_iter().next(), None);
+}
+
+#[test]
+fn test_into_iter_persistent() {
+    let mut v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    v.set_persistent(true);
+    assert!(v.is_persistent());
+    let path = v.path();
+    let capacity = v.capacity();
+
+    assert!(v.into_iter().eq([1, 2, 3]));
+    assert!(path.exists());
+
+    // File content can be mapped again.
+    let mut segment = Segment::<u64>::open_rw(&path, capacity).unwrap();
+    unsafe { segment.set_len(3) };
+    let v =
+        unsafe { MmapVec::from_segment(segment, DefaultSegmentBuilder::default(), path.clone()) };
+    assert_eq!(&v[..], [1, 2, 3]);
+
+    // Vec created from file is not persistent.
+    assert!(!v.is_persistent());
+    drop(v);
+    assert!(!path.exists());
+}
@@ -0,0 +1,47 @@
+#[cfg(feature = "background-flush")]
+use std::{fs, thread, time::Duration};
+
+#[cfg(feature = "background-flush")]
+use mmap_vec::MmapVec;
+
+#[test]
+fn test_flush() {
+    let v = mmap_vec::MmapVec::<u32>::new();
+    assert!(v.flush().is_ok());
+
+    let v = mmap_vec::MmapVec::<u32>::try_from([1, 2, 3]).unwrap();
+    assert!(v.flush().is_ok());
+}
+
+#[test]
+#[cfg(feature = "background-flush")]
+fn test_background_flush() {
+    let mut v = MmapVec::<u32>::new();
+    v.enable_background_flush(Duration::from_millis(1)).unwrap();
+
+    // Grow the vec multiple time while thread is running.
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    thread::sleep(Duration::from_millis(10));
+
+    // Check file content.
+    let content = fs::read(v.path()).unwrap();
+    assert_eq!(&content[..4], 0u32.to_ne_bytes());
+    assert_eq!(&content[4 * 9999..4 * 10_000], 9999u32.to_ne_bytes());
+
+    // Replace thread and then drop vec.
+    v.enable_background_flush(Duration::from_millis(5)).unwrap();
+    drop(v);
+}
+
+#[test]
+#[cfg(feature = "background-flush")]
+fn test_disable_background_flush() {
+    let mut v = MmapVec::<u32>::try_from([1, 2, 3]).unwrap();
+    v.enable_background_flush(Duration::from_secs(3600))
+        .unwrap();
+    v.disable_background_flush();
+    v.disable_background_flush();
+    assert_eq!(&v[..], [1, 2, 3]);
+}
@@ -0,0 +1,77 @@
+#[cfg(feature = "serde")]
+use mmap_vec::MmapVec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+#[derive(Debug, Serialize, Deserialize)]
+struct Prices {
+    name: String,
+    values: MmapVec<(u32, f64)>,
+    tags: Vec<String>,
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_struct_field_round_trip() {
+    let mut values = MmapVec::new();
+    for i in 0..10_000 {
+        values.push((i, i as f64 / 2.0)).unwrap();
+    }
+    let prices = Prices {
+        name: "ESZ4".to_string(),
+        values,
+        tags: vec!["cme".to_string(), "future".to_string()],
+    };
+
+    let bytes = bincode::serialize(&prices).unwrap();
+    let output: Prices = bincode::deserialize(&bytes).unwrap();
+
+    assert_eq!(output.name, prices.name);
+    assert_eq!(output.tags, prices.tags);
+    assert_eq!(output.values, prices.values);
+    assert_ne!(output.values.path(), prices.values.path());
+
+    // Bincode gives sequence len up front, so vec is created once with exact capacity.
+    assert_eq!(output.values.capacity(), 10_000);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_empty_round_trip() {
+    let prices = Prices {
+        name: String::new(),
+        values: MmapVec::new(),
+        tags: Vec::new(),
+    };
+
+    let bytes = bincode::serialize(&prices).unwrap();
+    let output: Prices = bincode::deserialize(&bytes).unwrap();
+    assert!(output.values.is_empty());
+    assert_eq!(output.values.capacity(), 0);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_truncated_input() {
+    let values = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    let bytes = bincode::serialize(&values).unwrap();
+
+    let result: Result<MmapVec<u64>, _> = bincode::deserialize(&bytes[..bytes.len() - 1]);
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_bogus_length_prefix() {
+    // Sequence claims `u64::MAX / 8` elements but holds a single one.
+    let mut bytes = bincode::serialize(&(u64::MAX / 8)).unwrap();
+    bytes.extend(bincode::serialize(&42u64).unwrap());
+
+    // Input must be read until its end instead of failing on pre-allocation.
+    let err = bincode::deserialize::<MmapVec<u64>>(&bytes).unwrap_err();
+    assert!(matches!(
+        *err,
+        bincode::ErrorKind::Io(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+}
@@ -1,19 +1,444 @@
-use mmap_vec::MmapVecBuilder;
+use std::{io, mem};
+
+use mmap_vec::{MmapVec, MmapVecBuilder, MmapVecError};
+
+/// Page size of running system, default capacity depends on it.
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
 
 #[test]
 fn test_capacity() {
+    // Default capacity fills exactly one page
     let v = MmapVecBuilder::<u8>::new().try_build().unwrap();
-    assert_eq!(v.capacity(), 4096);
+    assert_eq!(v.capacity(), page_size() / mem::size_of::<u8>());
 
     let v = MmapVecBuilder::<u64>::new().try_build().unwrap();
-    assert_eq!(v.capacity(), 512);
+    assert_eq!(v.capacity(), page_size() / mem::size_of::<u64>());
 
     let v = MmapVecBuilder::<i64>::new().try_build().unwrap();
-    assert_eq!(v.capacity(), 512);
+    assert_eq!(v.capacity(), page_size() / mem::size_of::<i64>());
 
     let v = MmapVecBuilder::<i64>::new()
         .capacity(128)
         .try_build()
         .unwrap();
     assert_eq!(v.capacity(), 128);
+
+    // Capacity is a size hint: vec is empty and does not grow until it is full
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(10_000)
+        .try_build()
+        .unwrap();
+    assert!(v.is_empty());
+    let ptr = v.as_ptr();
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.capacity(), 10_000);
+    assert_eq!(v.as_ptr(), ptr);
+}
+
+#[test]
+fn test_capacity_pages() {
+    let page_size = page_size();
+
+    let v = MmapVecBuilder::<u8>::new()
+        .capacity_pages(3)
+        .try_build()
+        .unwrap();
+    assert_eq!(v.capacity(), 3 * page_size);
+    assert_eq!(v.disk_size(), 3 * page_size);
+
+    let v = MmapVecBuilder::<u64>::new()
+        .capacity_pages(5)
+        .try_build()
+        .unwrap();
+    assert_eq!(v.capacity(), 5 * page_size / 8);
+    assert_eq!(v.disk_size(), 5 * page_size);
+
+    // Page size is not a multiple of 24, end of every page is unused
+    let v = MmapVecBuilder::<[u8; 24]>::new()
+        .capacity_pages(2)
+        .try_build()
+        .unwrap();
+    assert_eq!(v.capacity(), 2 * (page_size / 24));
+    assert!(v.disk_size() <= 2 * page_size);
+
+    // Elements bigger than a page
+    let v = MmapVecBuilder::<[u8; 5000]>::new()
+        .capacity_pages(5)
+        .try_build()
+        .unwrap();
+    assert_eq!(v.capacity(), 5 * page_size / 5000);
+    assert!(v.disk_size() <= 5 * page_size);
+}
+
+#[test]
+fn test_capacity_pages_overflow() {
+    let err = MmapVecBuilder::<u64>::new()
+        .capacity_pages(usize::MAX)
+        .try_build()
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(matches!(
+        err.get_ref().and_then(|err| err.downcast_ref()),
+        Some(MmapVecError::CapacityOverflow { .. })
+    ));
+
+    // Elements bigger than a page
+    let err = MmapVecBuilder::<[u8; 5000]>::new()
+        .capacity_pages(usize::MAX / 2)
+        .try_build()
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(matches!(
+        err.get_ref().and_then(|err| err.downcast_ref()),
+        Some(MmapVecError::CapacityOverflow { .. })
+    ));
+}
+
+#[test]
+fn test_segment_size_limit() {
+    const PAGE_SIZE: usize = 4096;
+
+    // Capacity above limit
+    let err = MmapVecBuilder::<u64>::new()
+        .capacity(100)
+        .segment_size_limit(799)
+        .try_build()
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(matches!(
+        err.get_ref().and_then(|err| err.downcast_ref()),
+        Some(MmapVecError::SegmentSizeExceeded {
+            size: 800,
+            limit: 799
+        })
+    ));
+
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(100)
+        .segment_size_limit(3 * PAGE_SIZE + 100)
+        .try_build()
+        .unwrap();
+    assert_eq!(v.segment_size_limit(), Some(3 * PAGE_SIZE + 100));
+    assert_eq!(v.capacity(), 100);
+
+    // Page rounding is capped to limit
+    v.reserve(1000).unwrap();
+    assert_eq!(v.capacity(), 1024);
+    v.reserve(1540).unwrap();
+    assert_eq!(v.capacity(), 1548);
+    assert!(v.reserve(1549).is_err());
+    assert!(v.reserve_exact(1549).is_err());
+    assert_eq!(v.capacity(), 1548);
+
+    // Push grows up to limit
+    for i in 0..1548 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.capacity(), 1548);
+    let err = v.push(1548).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "segment size exceeded: 12392 bytes requested, limit is 12388 bytes"
+    );
+    assert_eq!(v.len(), 1548);
+    assert!(v.iter().enumerate().all(|(i, x)| *x == i as u64));
+
+    // Limit is kept by derived vecs
+    let mut other = v.map_to_vec(|x| x + 1).unwrap();
+    assert_eq!(other.segment_size_limit(), Some(12388));
+    assert!(other.push(0).is_err());
+    let mut other = mmap_vec::concat(&[v]).unwrap();
+    assert_eq!(other.segment_size_limit(), Some(12388));
+    assert!(other.push(0).is_err());
+}
+
+#[test]
+fn test_warm_new_capacity() {
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(10)
+        .warm_new_capacity(true)
+        .try_build()
+        .unwrap();
+    assert_eq!(v.capacity(), 10);
+
+    for i in 0..5000 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.len(), 5000);
+    assert!(v.capacity() >= 5000);
+    assert_eq!(v.resident_pages().unwrap(), v.disk_size() / page_size());
+    assert!(v.iter().copied().eq(0..5000));
+
+    // Flag is kept by clone
+    let mut other = v.try_clone().unwrap();
+    other.reserve(100_000).unwrap();
+    assert!(other.iter().copied().eq(0..5000));
+}
+
+#[test]
+fn test_transparent_hugepages() {
+    const HUGE_PAGE_SIZE: usize = 2 << 20;
+
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(10)
+        .transparent_hugepages(true)
+        .try_build()
+        .unwrap();
+
+    // Small segments are page rounded
+    v.reserve(1000).unwrap();
+    assert_eq!(v.capacity(), 1024);
+
+    // Big segments are huge page rounded
+    v.reserve(HUGE_PAGE_SIZE / 8 + 1).unwrap();
+    assert_eq!(v.disk_size(), 2 * HUGE_PAGE_SIZE);
+
+    for i in 0..300_000 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(v.disk_size() % HUGE_PAGE_SIZE, 0);
+    assert!(v.iter().copied().eq(0..300_000));
+
+    // Limit is still honored
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(0)
+        .segment_size_limit(HUGE_PAGE_SIZE + 8)
+        .transparent_hugepages(true)
+        .try_build()
+        .unwrap();
+    v.reserve(HUGE_PAGE_SIZE / 8 + 1).unwrap();
+    assert_eq!(v.capacity(), HUGE_PAGE_SIZE / 8 + 1);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_guard_pages() {
+    /// Get permissions of mapping starting at `addr`.
+    fn mapping_perms(addr: usize) -> Option<String> {
+        let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+        maps.lines().find_map(|line| {
+            let (range, rest) = line.split_once(' ')?;
+            let (start, _) = range.split_once('-')?;
+            (usize::from_str_radix(start, 16).ok()? == addr).then(|| rest[..4].to_string())
+        })
+    }
+
+    fn guard_addr(v: &MmapVec<u64>) -> usize {
+        let page_size = page_size();
+        v.as_ptr() as usize + (v.disk_size() + page_size - 1) / page_size * page_size
+    }
+
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(100)
+        .guard_pages(true)
+        .try_build()
+        .unwrap();
+    v.push(1).unwrap();
+    assert_eq!(mapping_perms(guard_addr(&v)).as_deref(), Some("---s"));
+
+    // Guard page follows segment when it grows
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    assert_eq!(mapping_perms(guard_addr(&v)).as_deref(), Some("---s"));
+
+    // And when it shrinks
+    v.truncate(10);
+    v.shrink_to_fit().unwrap();
+    assert_eq!(v.capacity(), page_size() / 8);
+    assert_eq!(mapping_perms(guard_addr(&v)).as_deref(), Some("---s"));
+    assert_eq!(&v[..2], [1, 0]);
+
+    // Guard page is released when vec is decomposed in raw parts
+    let addr = guard_addr(&v);
+    let (ptr, len, capacity, path) = v.into_raw_parts();
+    assert_ne!(mapping_perms(addr).as_deref(), Some("---s"));
+    let v = unsafe { MmapVec::from_raw_parts(ptr, len, capacity, path) };
+    assert_eq!(&v[..2], [1, 0]);
+
+    // Guard page is released with the segment
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(100)
+        .guard_pages(true)
+        .try_build()
+        .unwrap();
+    v.push(1).unwrap();
+
+    // Vecs derived from it get a guard page too
+    let other = v.try_clone().unwrap();
+    assert_eq!(mapping_perms(guard_addr(&other)).as_deref(), Some("---s"));
+    let other = v.map_to_vec(|x| x + 1).unwrap();
+    assert_eq!(mapping_perms(guard_addr(&other)).as_deref(), Some("---s"));
+    let other = v.try_clone_into_dir(std::env::temp_dir()).unwrap();
+    assert_eq!(mapping_perms(guard_addr(&other)).as_deref(), Some("---s"));
+    let other = mmap_vec::concat(&[v.try_clone().unwrap(), other]).unwrap();
+    assert_eq!(mapping_perms(guard_addr(&other)).as_deref(), Some("---s"));
+    for chunk in v.try_clone().unwrap().into_chunk_files(1).unwrap() {
+        assert_eq!(mapping_perms(guard_addr(&chunk)).as_deref(), Some("---s"));
+    }
+
+    let addr = guard_addr(&v);
+    drop(v);
+    assert_ne!(mapping_perms(addr).as_deref(), Some("---s"));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_dont_fork() {
+    /// Get `VmFlags` of mapping starting at `addr` (`dc` stands for "do not copy on fork").
+    fn vm_flags(addr: usize) -> Option<Vec<String>> {
+        let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+        let header = format!("{addr:x}-");
+        let mut lines = smaps.lines().skip_while(|line| !line.starts_with(&header));
+        lines.next()?;
+        let flags = lines.find_map(|line| line.strip_prefix("VmFlags:"))?;
+        Some(flags.split_whitespace().map(String::from).collect())
+    }
+
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(100)
+        .dont_fork(true)
+        .try_build()
+        .unwrap();
+    v.push(1).unwrap();
+    assert!(vm_flags(v.as_ptr() as usize)
+        .unwrap()
+        .contains(&"dc".to_string()));
+
+    // Flag is applied to new mappings
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    assert!(vm_flags(v.as_ptr() as usize)
+        .unwrap()
+        .contains(&"dc".to_string()));
+
+    v.truncate(10);
+    v.shrink_to_fit().unwrap();
+    assert!(vm_flags(v.as_ptr() as usize)
+        .unwrap()
+        .contains(&"dc".to_string()));
+
+    // Disabled by default
+    let v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    assert!(!vm_flags(v.as_ptr() as usize)
+        .unwrap()
+        .contains(&"dc".to_string()));
+}
+
+#[test]
+fn test_check_free_space() {
+    // 128TB does not fit on test file system
+    const HUGE_CAPACITY: usize = 1 << 44;
+
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(100)
+        .check_free_space(true)
+        .try_build()
+        .unwrap();
+
+    // Small grows are fine
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    let capacity = v.capacity();
+
+    // Too big grow fails early, vec is left intact
+    let err = v.reserve_exact(HUGE_CAPACITY).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        io::Error::from_raw_os_error(libc::ENOSPC).kind()
+    );
+    assert!(err.to_string().starts_with("insufficient space: "));
+    assert!(matches!(
+        err.get_ref().and_then(|err| err.downcast_ref()),
+        Some(MmapVecError::InsufficientSpace { required, .. }) if *required == ((10_000 + HUGE_CAPACITY) * 8 - capacity * 8) as u64
+    ));
+    assert_eq!(v.capacity(), capacity);
+    assert!(v.iter().copied().eq(0..10_000));
+    assert_eq!(
+        std::fs::metadata(v.path()).unwrap().len(),
+        v.disk_size() as u64
+    );
+
+    // Building a vec checks free space too
+    let err = MmapVecBuilder::<u64>::new()
+        .capacity(HUGE_CAPACITY)
+        .check_free_space(true)
+        .try_build()
+        .unwrap_err();
+    assert!(err.to_string().starts_with("insufficient space: "));
+}
+
+#[test]
+#[cfg(all(feature = "numa", target_os = "linux"))]
+fn test_numa_interleave() {
+    /// Get memory policy of mapping starting at `addr`.
+    fn mem_policy(addr: usize) -> Option<String> {
+        let numa_maps = std::fs::read_to_string("/proc/self/numa_maps").ok()?;
+        let header = format!("{addr:x} ");
+        numa_maps.lines().find_map(|line| {
+            let policy = line.strip_prefix(&header)?.split_whitespace().next()?;
+            Some(policy.to_string())
+        })
+    }
+
+    let mut v = MmapVecBuilder::<u64>::new()
+        .capacity(100)
+        .numa_interleave(true)
+        .try_build()
+        .unwrap();
+    v.push(1).unwrap();
+
+    // Kernel without NUMA support does not expose policies
+    let Some(policy) = mem_policy(v.as_ptr() as usize) else {
+        return;
+    };
+    assert!(policy.starts_with("interleave"), "{policy}");
+
+    // Policy is applied to new mappings
+    for i in 0..10_000 {
+        v.push(i).unwrap();
+    }
+    let policy = mem_policy(v.as_ptr() as usize).unwrap();
+    assert!(policy.starts_with("interleave"), "{policy}");
+
+    // Disabled by default
+    let v = MmapVec::<u64>::try_from([1, 2, 3]).unwrap();
+    assert_eq!(mem_policy(v.as_ptr() as usize).as_deref(), Some("default"));
+}
+
+#[test]
+fn test_poison_spare() {
+    /// Read spare capacity bytes of `v`.
+    fn spare_bytes(v: &MmapVec<u32>) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(v.as_ptr().add(v.len()).cast(), (v.capacity() - v.len()) * 4)
+        }
+    }
+
+    let mut v = MmapVecBuilder::<u32>::new()
+        .capacity(10)
+        .poison_spare(0xAA)
+        .try_build()
+        .unwrap();
+    assert!(spare_bytes(&v).iter().all(|b| *b == 0xAA));
+
+    // Live data is kept on grow, new capacity is poisoned
+    for i in 0..5000 {
+        v.push(i).unwrap();
+    }
+    assert!(v.iter().copied().eq(0..5000));
+    assert!(spare_bytes(&v).iter().all(|b| *b == 0xAA));
+
+    unsafe { v.assume_init_len(v.len() + 1) };
+    assert_eq!(v[5000], 0xAAAA_AAAA);
+
+    // Disabled by default
+    let v = MmapVec::<u32>::with_capacity(10).unwrap();
+    assert!(spare_bytes(&v).iter().all(|b| *b == 0));
 }
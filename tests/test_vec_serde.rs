@@ -29,3 +29,35 @@ fn test_deserialize() {
         assert_eq!(&vec[..], [8, 6, 42]);
     }
 }
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_deserialize_without_size_hint() {
+    // JSON sequences does not provide any size hint.
+    let vec: MmapVec<u32> = serde_json::from_str("[1,2,3]").unwrap();
+    assert_eq!(&vec[..], [1, 2, 3]);
+    assert_eq!(vec.capacity(), mmap_vec::round_capacity_to_page::<u32>(1));
+
+    let input = serde_json::to_string(&(0..5000).collect::<Vec<u32>>()).unwrap();
+    let vec: MmapVec<u32> = serde_json::from_str(&input).unwrap();
+    assert_eq!(vec.len(), 5000);
+    assert!(vec.iter().enumerate().all(|(i, x)| *x == i as u32));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_round_trip_nested() {
+    let mut input = Vec::new();
+    for i in 0..3 {
+        input.push(MmapVec::<(u32, f64)>::try_from([(i, 0.5), (i + 1, 1.5)]).unwrap());
+    }
+
+    let json = serde_json::to_string(&input).unwrap();
+    assert_eq!(
+        json,
+        "[[[0,0.5],[1,1.5]],[[1,0.5],[2,1.5]],[[2,0.5],[3,1.5]]]"
+    );
+
+    let output: Vec<MmapVec<(u32, f64)>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(output, input);
+}
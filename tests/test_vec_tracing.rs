@@ -0,0 +1,86 @@
+#[cfg(feature = "tracing")]
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+#[cfg(feature = "tracing")]
+use mmap_vec::MmapVec;
+#[cfg(feature = "tracing")]
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+/// Subscriber recording message of every event and name of every span.
+#[cfg(feature = "tracing")]
+#[derive(Default, Clone)]
+struct Recorder {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<Vec<String>>>,
+    messages: Arc<Mutex<Vec<String>>>,
+}
+
+#[cfg(feature = "tracing")]
+struct MessageVisitor<'a>(&'a mut String);
+
+#[cfg(feature = "tracing")]
+impl Visit for MessageVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            *self.0 = format!("{value:?}");
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Subscriber for Recorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        self.spans
+            .lock()
+            .unwrap()
+            .push(span.metadata().name().to_string());
+        span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut message = String::new();
+        event.record(&mut MessageVisitor(&mut message));
+        self.messages.lock().unwrap().push(message);
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+#[test]
+#[cfg(feature = "tracing")]
+fn test_reserve_and_drop_events() {
+    let recorder = Recorder::default();
+
+    tracing::subscriber::with_default(recorder.clone(), || {
+        // Vec that never allocated has nothing to report.
+        drop(MmapVec::<u64>::new());
+        assert!(recorder.messages.lock().unwrap().is_empty());
+
+        let mut v = MmapVec::<u64>::new();
+        v.reserve(10).unwrap();
+        v.reserve(10_000).unwrap();
+        drop(v);
+    });
+
+    assert_eq!(*recorder.spans.lock().unwrap(), ["reserve", "reserve"]);
+    assert_eq!(
+        *recorder.messages.lock().unwrap(),
+        ["mmap vec grown", "mmap vec grown", "mmap vec file removed"]
+    );
+}